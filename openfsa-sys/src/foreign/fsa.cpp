@@ -1,5 +1,8 @@
 #include <fst/fstlib.h>
 #include <vector>
+#include <climits>
+#include <set>
+#include <map>
 #include <iostream>
 #include <sstream>
 #include <string>
@@ -23,6 +26,39 @@ const fst::Fst<fst::StdArc>* reinterpret(const struct fsa_t *wrapper) {
     return NULL;
 } 
 
+// wraps an immutable copy of an FSA
+// in contrast to CompactFst, ConstFst does not cache and is thread-safe for reading
+struct fsa_t freeze(const fst::Fst<fst::StdArc> &fsa) {
+    struct fsa_t wrapper = { CONST, new fst::ConstFst<fst::StdArc>(fsa) };
+    return wrapper;
+}
+
+fst::EncodeMapper<fst::StdArc>* mapper(const struct encoder_t *encoder) {
+    return static_cast<fst::EncodeMapper<fst::StdArc>*>(encoder->mapper);
+}
+
+// removes epsilons, determinizes and pushes the weights of an FSA in the log semiring,
+// s.t. FSA that recognize the same weighted language only differ in the numbering of states
+fst::VectorFst<fst::LogArc> normalize(const fst::Fst<fst::StdArc> &fsa) {
+    fst::VectorFst<fst::LogArc> log_fsa, log_det;
+    fst::ArcMap(fsa, &log_fsa, fst::WeightConvertMapper<fst::StdArc, fst::LogArc>());
+    fst::RmEpsilon(&log_fsa);
+    fst::Determinize(log_fsa, &log_det);
+    fst::Push(&log_det, fst::REWEIGHT_TO_INITIAL);
+    return log_det;
+}
+
+// compares arcs by their target states
+struct NextStateCompare {
+    bool operator()(const fst::StdArc &a, const fst::StdArc &b) const {
+        return a.nextstate < b.nextstate;
+    }
+
+    uint64 Properties(uint64 props) const {
+        return props & fst::kArcSortProperties & ~(fst::kILabelSorted | fst::kOLabelSorted);
+    }
+};
+
 extern "C" {
 
     struct fsa_t fsa_from_string(const struct vec_t *binary){
@@ -30,11 +66,15 @@ extern "C" {
         std::string binary_string(static_cast<char*>(binary->first), binary->length);
         stream.str(binary_string);
 
-        struct fsa_t wrapper = { 
-            COMPACT,
-            fst::CompactFst<fst::StdArc, fst::AcceptorCompactor<fst::StdArc> >::Read(stream, fst::FstReadOptions())
-        };
+        // Read returns NULL if the binary string is malformed
+        fst::CompactFst<fst::StdArc, fst::AcceptorCompactor<fst::StdArc> > *compact = fst::CompactFst<fst::StdArc, fst::AcceptorCompactor<fst::StdArc> >::Read(stream, fst::FstReadOptions());
+        if (compact == NULL){
+            struct fsa_t wrapper = { CONST, NULL };
+            return wrapper;
+        }
 
+        struct fsa_t wrapper = freeze(*compact);
+        delete compact;
         return wrapper;
     }
     
@@ -49,7 +89,7 @@ extern "C" {
         // allocating std::string in heap would give use a const pointer
         std::vector<char> *cstr = new std::vector<char>(binary_string.c_str(), binary_string.c_str() + binary_string.length());
 
-        struct vec_t list = { CHAR, cstr, &((*cstr)[0]), binary_string.length() };
+        struct vec_t list = { CHAR, cstr, cstr->data(), binary_string.length() };
         return list;
     }
 
@@ -59,7 +99,7 @@ extern "C" {
         
         fst::StdVectorFst mut;
         fsa_arc *arcs = static_cast<fsa_arc*>(arc_list->first);
-        int *finals = static_cast<int*>(final_states->first);
+        fsa_final *finals = static_cast<fsa_final*>(final_states->first);
         
         // add states
         for (int i = 0; i < states; i++){
@@ -71,19 +111,16 @@ extern "C" {
                 arcs[i].from_state, fst::StdArc(arcs[i].label, arcs[i].label, arcs[i].weight, arcs[i].to_state)
             );
         }
-        // set final states without weight
+        // set final states with their weights
         for (size_t i = 0; i < final_states->length; i++){
-            mut.SetFinal(finals[i], 0.0);
+            mut.SetFinal(finals[i].state, finals[i].weight);
         }
         // start is always 0
         mut.SetStart(0);
 
         fst::ArcSort(&mut, fst::ILabelCompare<fst::StdArc>());
 
-        fst::CompactFst<fst::StdArc, fst::AcceptorCompactor<fst::StdArc> > *imut = new fst::CompactFst<fst::StdArc, fst::AcceptorCompactor<fst::StdArc> >(mut);
-        
-        struct fsa_t wrapper = { COMPACT, imut };
-        return wrapper;
+        return freeze(mut);
     }
 
     struct vec_t fsa_to_arc_list(const struct fsa_t *wrapper){
@@ -102,45 +139,377 @@ extern "C" {
             }
         }
 
-        struct vec_t al = { ARC, vec, &(*vec)[0], vec->size() };
+        struct vec_t al = { ARC, vec, vec->data(), vec->size() };
         return al;
     }
 
-    struct fsa_t fsa_n_best(const struct fsa_t *fsa, int n){
+    struct vec_t fsa_arcs_from(const struct fsa_t *wrapper, int state){
+        std::vector<struct fsa_arc> *vec = new std::vector<struct fsa_arc>();
+        const fst::Fst<fst::StdArc> *fsa = reinterpret(wrapper);
+
+        // there are no arcs leaving states that do not exist
+        if (state >= 0 && state < fst::CountStates(*fsa)){
+            struct fsa_arc carc;
+            for (fst::ArcIterator<fst::StdFst> arc(*fsa, state); !arc.Done(); arc.Next()){
+                carc.from_state = state;
+                carc.to_state = arc.Value().nextstate;
+                carc.label = arc.Value().ilabel;
+                carc.weight = arc.Value().weight.Value();
+
+                vec->push_back(carc);
+            }
+        }
+
+        struct vec_t al = { ARC, vec, vec->data(), vec->size() };
+        return al;
+    }
+
+    struct fsa_t fsa_n_best(const struct fsa_t *fsa, int n, int unique){
         fst::StdVectorFst nbest;
-        fst::ShortestPath(*reinterpret(fsa), &nbest, n);
+        // the runs of distinct words are determined after determinization
+        fst::ShortestPath(*reinterpret(fsa), &nbest, n, unique != 0);
         fst::RmEpsilon(&nbest);
         
-        struct fsa_t ret = { 
-            COMPACT,
-            new fst::CompactFst<fst::StdArc, fst::AcceptorCompactor<fst::StdArc> >(nbest)
-        };
-        return ret;
+        return freeze(nbest);
     }
 
     struct fsa_t fsa_intersect(const struct fsa_t *a, const struct fsa_t *b){
+        struct fsa_t ret = { CONST, NULL };
+        if (a->fsa == NULL || b->fsa == NULL){
+            return ret;
+        }
+
         fst::StdVectorFst inter;
         fst::Intersect(*reinterpret(a), *reinterpret(b), &inter);
 
-        struct fsa_t ret = { 
-            COMPACT,
-            new fst::CompactFst<fst::StdArc, fst::AcceptorCompactor<fst::StdArc> >(inter)
-        };
-        return ret;
+        // a failed intersection is signaled by the error property
+        if (inter.Properties(fst::kError, false)){
+            return ret;
+        }
+        return freeze(inter);
     }
 
     struct fsa_t fsa_difference(const struct fsa_t *a, const struct fsa_t *b){
+        struct fsa_t ret = { CONST, NULL };
+        if (a->fsa == NULL || b->fsa == NULL){
+            return ret;
+        }
+
         fst::ArcMapFst<fst::StdArc, fst::StdArc, fst::RmWeightMapper<fst::StdArc> > c(*reinterpret(b), fst::RmWeightMapper<fst::StdArc>());
         fst::DeterminizeFst<fst::StdArc> d(c);
         fst::DifferenceFst<fst::StdArc> difference(*reinterpret(a), d);
 
-        struct fsa_t ret = {
-            COMPACT,
-            new fst::CompactFst<fst::StdArc, fst::AcceptorCompactor<fst::StdArc> >(difference)
-        };
+        // the lazy difference signals failures by the error property after expansion
+        fst::ConstFst<fst::StdArc> *expanded = new fst::ConstFst<fst::StdArc>(difference);
+        if (expanded->Properties(fst::kError, false)){
+            delete expanded;
+        } else {
+            ret.fsa = expanded;
+        }
         return ret;
     }
 
+    struct fsa_t fsa_union(const struct fsa_t *a, const struct fsa_t *b){
+        fst::StdVectorFst uni(*reinterpret(a));
+        fst::Union(&uni, *reinterpret(b));
+        // Union connects both initial states with an epsilon arc
+        fst::RmEpsilon(&uni);
+        fst::ArcSort(&uni, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(uni);
+    }
+
+    struct fsa_t fsa_union_all(const struct vec_t *fsas){
+        const struct fsa_t *const *parts = static_cast<const struct fsa_t *const *>(fsas->first);
+
+        // the union of no fsa recognizes the empty language
+        fst::StdVectorFst uni;
+        uni.SetStart(uni.AddState());
+        for (size_t i = 0; i < fsas->length; i++){
+            // the new initial state of the first union is reused by all further ones
+            fst::Union(&uni, *reinterpret(parts[i]));
+        }
+        fst::RmEpsilon(&uni);
+        fst::ArcSort(&uni, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(uni);
+    }
+
+    struct fsa_t fsa_concat(const struct fsa_t *a, const struct fsa_t *b){
+        fst::StdVectorFst concat(*reinterpret(a));
+        fst::Concat(&concat, *reinterpret(b));
+        // Concat connects the final states of a with the initial state of b using epsilon arcs
+        fst::RmEpsilon(&concat);
+        fst::ArcSort(&concat, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(concat);
+    }
+
+    struct fsa_t fsa_concat_all(const struct vec_t *fsas){
+        const struct fsa_t *const *parts = static_cast<const struct fsa_t *const *>(fsas->first);
+
+        // the concatenation of no fsa recognizes the empty word
+        fst::StdVectorFst concat;
+        concat.SetStart(concat.AddState());
+        concat.SetFinal(concat.Start(), fst::TropicalWeight::One());
+        for (size_t i = 0; i < fsas->length; i++){
+            fst::Concat(&concat, *reinterpret(parts[i]));
+        }
+        // the epsilon arcs between all fsas are removed at once
+        fst::RmEpsilon(&concat);
+        fst::ArcSort(&concat, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(concat);
+    }
+
+    struct fsa_t fsa_closure(const struct fsa_t *fsa, int plus){
+        fst::StdVectorFst closure(*reinterpret(fsa));
+        fst::Closure(&closure, plus ? fst::CLOSURE_PLUS : fst::CLOSURE_STAR);
+        // Closure connects the final states with the initial state using epsilon arcs
+        fst::RmEpsilon(&closure);
+        fst::ArcSort(&closure, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(closure);
+    }
+
+    struct fsa_t fsa_reverse(const struct fsa_t *fsa){
+        fst::StdVectorFst reversed;
+        fst::Reverse(*reinterpret(fsa), &reversed);
+        // Reverse adds a super-initial state with epsilon arcs to the former final states
+        fst::RmEpsilon(&reversed);
+        fst::ArcSort(&reversed, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(reversed);
+    }
+
+    struct fsa_t fsa_determinize(const struct fsa_t *fsa){
+        // determinize in the log semiring, s.t. the weights of equivalent runs are summed up
+        fst::VectorFst<fst::LogArc> log_fsa, log_det;
+        fst::ArcMap(*reinterpret(fsa), &log_fsa, fst::WeightConvertMapper<fst::StdArc, fst::LogArc>());
+        fst::Determinize(log_fsa, &log_det);
+
+        fst::StdVectorFst det;
+        fst::ArcMap(log_det, &det, fst::WeightConvertMapper<fst::LogArc, fst::StdArc>());
+        fst::ArcSort(&det, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(det);
+    }
+
+    struct fsa_t fsa_push(const struct fsa_t *fsa, int to_final){
+        // push in the log semiring, s.t. the pushed weights are sums over all runs
+        fst::VectorFst<fst::LogArc> log_fsa;
+        fst::ArcMap(*reinterpret(fsa), &log_fsa, fst::WeightConvertMapper<fst::StdArc, fst::LogArc>());
+        fst::Push(&log_fsa, to_final ? fst::REWEIGHT_TO_FINAL : fst::REWEIGHT_TO_INITIAL);
+
+        fst::StdVectorFst pushed;
+        fst::ArcMap(log_fsa, &pushed, fst::WeightConvertMapper<fst::LogArc, fst::StdArc>());
+        fst::ArcSort(&pushed, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(pushed);
+    }
+
+    struct fsa_t fsa_normalize(const struct fsa_t *fsa){
+        // push towards the initial state and remove the total weight
+        // that is left at the initial state
+        fst::VectorFst<fst::LogArc> log_fsa;
+        fst::ArcMap(*reinterpret(fsa), &log_fsa, fst::WeightConvertMapper<fst::StdArc, fst::LogArc>());
+        fst::Push(&log_fsa, fst::REWEIGHT_TO_INITIAL, fst::kShortestDelta, true);
+
+        fst::StdVectorFst normalized;
+        fst::ArcMap(log_fsa, &normalized, fst::WeightConvertMapper<fst::LogArc, fst::StdArc>());
+        fst::ArcSort(&normalized, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(normalized);
+    }
+
+    struct fsa_t fsa_arc_sort(const struct fsa_t *fsa, int by_state){
+        fst::StdVectorFst sorted(*reinterpret(fsa));
+        if (by_state){
+            fst::ArcSort(&sorted, NextStateCompare());
+        } else {
+            fst::ArcSort(&sorted, fst::ILabelCompare<fst::StdArc>());
+        }
+
+        return freeze(sorted);
+    }
+
+    struct fsa_t fsa_topsort(const struct fsa_t *fsa){
+        fst::StdVectorFst sorted(*reinterpret(fsa));
+
+        // TopSort leaves a cyclic fsa unchanged
+        if (!fst::TopSort(&sorted)){
+            struct fsa_t ret = { CONST, NULL };
+            return ret;
+        }
+        return freeze(sorted);
+    }
+
+    int fsa_equivalent(const struct fsa_t *a, const struct fsa_t *b){
+        if (a->fsa == NULL || b->fsa == NULL){
+            return 0;
+        }
+        return fst::Equivalent(normalize(*reinterpret(a)), normalize(*reinterpret(b))) ? 1 : 0;
+    }
+
+    unsigned long long fsa_properties(const struct fsa_t *fsa, unsigned long long mask){
+        // compute the properties that are not known yet
+        return reinterpret(fsa)->Properties(mask, true) & mask;
+    }
+
+    unsigned long long fsa_count_paths(const struct fsa_t *fsa){
+        // TopSort fails if there is a cycle
+        fst::StdVectorFst sorted(*reinterpret(fsa));
+        if (!fst::TopSort(&sorted)){
+            return ULLONG_MAX;
+        }
+        if (sorted.Start() == fst::kNoStateId){
+            return 0;
+        }
+
+        // after TopSort, each arc leads to a greater state
+        // the sums saturate at ULLONG_MAX, which marks too many runs
+        std::vector<unsigned long long> paths(sorted.NumStates(), 0);
+        for (int state = sorted.NumStates() - 1; state >= 0; state--){
+            paths[state] = sorted.Final(state) != fst::TropicalWeight::Zero() ? 1 : 0;
+            for (fst::ArcIterator<fst::StdVectorFst> arc(sorted, state); !arc.Done(); arc.Next()){
+                unsigned long long next = paths[arc.Value().nextstate];
+                paths[state] = next < ULLONG_MAX - paths[state] ? paths[state] + next : ULLONG_MAX;
+            }
+        }
+        return paths[sorted.Start()];
+    }
+
+    struct fsa_t fsa_minimize(const struct fsa_t *fsa){
+        fst::StdVectorFst min(*reinterpret(fsa));
+        fst::Minimize(&min);
+        fst::ArcSort(&min, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(min);
+    }
+
+    struct fsa_t fsa_remove_epsilon(const struct fsa_t *fsa){
+        // remove epsilons in the log semiring, s.t. the weights of epsilon paths are summed up
+        fst::VectorFst<fst::LogArc> log_fsa;
+        fst::ArcMap(*reinterpret(fsa), &log_fsa, fst::WeightConvertMapper<fst::StdArc, fst::LogArc>());
+        fst::RmEpsilon(&log_fsa);
+
+        fst::StdVectorFst rmeps;
+        fst::ArcMap(log_fsa, &rmeps, fst::WeightConvertMapper<fst::LogArc, fst::StdArc>());
+        fst::ArcSort(&rmeps, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(rmeps);
+    }
+
+    struct fsa_t fsa_connect(const struct fsa_t *fsa){
+        fst::StdVectorFst connected(*reinterpret(fsa));
+        fst::Connect(&connected);
+
+        return freeze(connected);
+    }
+
+    struct fsa_t fsa_prune(const struct fsa_t *fsa, float threshold){
+        fst::StdVectorFst pruned(*reinterpret(fsa));
+        fst::Prune(&pruned, fst::TropicalWeight(threshold));
+
+        return freeze(pruned);
+    }
+
+    struct fsa_t fsa_retain_arcs(const struct fsa_t *fsa, const struct vec_t *keep){
+        fst::StdVectorFst retained(*reinterpret(fsa));
+        int *mask = static_cast<int*>(keep->first);
+
+        // the arcs are visited in the same order as in fsa_to_arc_list
+        size_t i = 0;
+        for (fst::StateIterator<fst::StdVectorFst> state(retained); !state.Done(); state.Next()){
+            std::vector<fst::StdArc> arcs;
+            for (fst::ArcIterator<fst::StdVectorFst> arc(retained, state.Value()); !arc.Done(); arc.Next(), i++){
+                if (i < keep->length && mask[i]){
+                    arcs.push_back(arc.Value());
+                }
+            }
+            retained.DeleteArcs(state.Value());
+            for (size_t j = 0; j < arcs.size(); j++){
+                retained.AddArc(state.Value(), arcs[j]);
+            }
+        }
+
+        return freeze(retained);
+    }
+
+    struct fsa_t fsa_reweight(const struct fsa_t *fsa, const struct vec_t *arc_weights, const struct vec_t *final_weights){
+        fst::StdVectorFst reweighted(*reinterpret(fsa));
+        float *weights = static_cast<float*>(arc_weights->first);
+        fsa_final *finals = static_cast<fsa_final*>(final_weights->first);
+
+        // the arcs are visited in the same order as in fsa_to_arc_list
+        size_t i = 0;
+        for (fst::StateIterator<fst::StdVectorFst> state(reweighted); !state.Done(); state.Next()){
+            for (fst::MutableArcIterator<fst::StdVectorFst> arc(&reweighted, state.Value()); !arc.Done() && i < arc_weights->length; arc.Next(), i++){
+                fst::StdArc a = arc.Value();
+                a.weight = weights[i];
+                arc.SetValue(a);
+            }
+        }
+        for (size_t j = 0; j < final_weights->length; j++){
+            reweighted.SetFinal(finals[j].state, finals[j].weight);
+        }
+
+        return freeze(reweighted);
+    }
+
+    struct fsa_t fsa_relabel(const struct fsa_t *fsa, const struct vec_t *label_map){
+        fst::StdVectorFst relabeled(*reinterpret(fsa));
+        int *labels = static_cast<int*>(label_map->first);
+
+        // replace each label l by labels[l], epsilon is mapped to itself
+        for (fst::StateIterator<fst::StdVectorFst> state(relabeled); !state.Done(); state.Next()){
+            for (fst::MutableArcIterator<fst::StdVectorFst> arc(&relabeled, state.Value()); !arc.Done(); arc.Next()){
+                fst::StdArc a = arc.Value();
+                a.ilabel = labels[a.ilabel];
+                a.olabel = labels[a.olabel];
+                arc.SetValue(a);
+            }
+        }
+        fst::ArcSort(&relabeled, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(relabeled);
+    }
+
+    struct encoder_t encoder_new(){
+        // each pair of a label and a weight is encoded as a single label
+        struct encoder_t encoder = { new fst::EncodeMapper<fst::StdArc>(fst::kEncodeLabels | fst::kEncodeWeights, fst::ENCODE) };
+        return encoder;
+    }
+
+    int encoder_size(const struct encoder_t *encoder){
+        return mapper(encoder)->Size();
+    }
+
+    struct fsa_t fsa_encode(const struct fsa_t *fsa, struct encoder_t *encoder){
+        fst::StdVectorFst encoded(*reinterpret(fsa));
+        fst::Encode(&encoded, mapper(encoder));
+        fst::ArcSort(&encoded, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(encoded);
+    }
+
+    struct fsa_t fsa_decode(const struct fsa_t *fsa, const struct encoder_t *encoder){
+        struct fsa_t ret = { CONST, NULL };
+        fst::StdVectorFst decoded(*reinterpret(fsa));
+        fst::Decode(&decoded, *mapper(encoder));
+
+        // labels that are not in the table are signaled by the error property
+        if (decoded.Properties(fst::kError, false)){
+            return ret;
+        }
+        fst::ArcSort(&decoded, fst::ILabelCompare<fst::StdArc>());
+        return freeze(decoded);
+    }
+
+    void encoder_free(const struct encoder_t *encoder){
+        delete mapper(encoder);
+    }
+
     void fsa_free(const struct fsa_t *fsa){
         delete reinterpret(fsa);
     }
@@ -149,6 +518,91 @@ extern "C" {
         return reinterpret(fsa)->Start();
     }
 
+    int fsa_num_states(const struct fsa_t *fsa){
+        // uses NumStates for expanded FSA and counts the states otherwise
+        return fst::CountStates(*reinterpret(fsa));
+    }
+
+    int fsa_num_arcs(const struct fsa_t *fsa){
+        const fst::Fst<fst::StdArc> *fst = reinterpret(fsa);
+
+        int arcs = 0;
+        for (fst::StateIterator<fst::StdFst> state(*fst); !state.Done(); state.Next()){
+            arcs += fst->NumArcs(state.Value());
+        }
+        return arcs;
+    }
+
+    int fsa_is_empty(const struct fsa_t *fsa){
+        const fst::Fst<fst::StdArc> *fst = reinterpret(fsa);
+        if (fst->Start() == fst::kNoStateId){
+            return 1;
+        }
+
+        // depth-first search for a final state that is reachable from the initial state
+        std::vector<int> agenda(1, fst->Start());
+        std::set<int> visited(agenda.begin(), agenda.end());
+        while (!agenda.empty()){
+            int state = agenda.back();
+            agenda.pop_back();
+            if (fst->Final(state) != fst::TropicalWeight::Zero()){
+                return 0;
+            }
+            for (fst::ArcIterator<fst::StdFst> arc(*fst, state); !arc.Done(); arc.Next()){
+                if (visited.insert(arc.Value().nextstate).second){
+                    agenda.push_back(arc.Value().nextstate);
+                }
+            }
+        }
+        return 1;
+    }
+
+    float fsa_weight_of(const struct fsa_t *fsa, const struct vec_t *word){
+        const fst::Fst<fst::StdArc> *fst = reinterpret(fsa);
+        int *labels = static_cast<int*>(word->first);
+        if (fst->Start() == fst::kNoStateId){
+            return fst::LogWeight::Zero().Value();
+        }
+
+        // weights of all runs reading a prefix of the word, summed up per state in the log semiring
+        std::map<int, fst::LogWeight> current;
+        current.insert(std::make_pair(fst->Start(), fst::LogWeight::One()));
+        for (size_t i = 0; i < word->length; i++){
+            std::map<int, fst::LogWeight> next;
+            for (std::map<int, fst::LogWeight>::iterator run = current.begin(); run != current.end(); ++run){
+                for (fst::ArcIterator<fst::StdFst> arc(*fst, run->first); !arc.Done(); arc.Next()){
+                    if (arc.Value().ilabel != labels[i]){
+                        continue;
+                    }
+                    fst::LogWeight weight = fst::Times(run->second, fst::LogWeight(arc.Value().weight.Value()));
+                    std::map<int, fst::LogWeight>::iterator known = next.find(arc.Value().nextstate);
+                    if (known == next.end()){
+                        next.insert(std::make_pair(arc.Value().nextstate, weight));
+                    } else {
+                        known->second = fst::Plus(known->second, weight);
+                    }
+                }
+            }
+            current.swap(next);
+        }
+
+        fst::LogWeight total = fst::LogWeight::Zero();
+        for (std::map<int, fst::LogWeight>::iterator run = current.begin(); run != current.end(); ++run){
+            total = fst::Plus(total, fst::Times(run->second, fst::LogWeight(fst->Final(run->first).Value())));
+        }
+        return total.Value();
+    }
+
+    int fsa_is_final(const struct fsa_t *fsa, int state){
+        const fst::Fst<fst::StdArc> *fst = reinterpret(fsa);
+
+        // states that do not exist are not final
+        if (state < 0 || state >= fst::CountStates(*fst)){
+            return 0;
+        }
+        return fst->Final(state) != fst::TropicalWeight::Zero();
+    }
+
     struct vec_t fsa_final_states(const struct fsa_t *fsa){
         // allocate vector on stack
         std::vector<int> *final_states = new std::vector<int>;
@@ -162,7 +616,127 @@ extern "C" {
         }
 
         // return list as pointer × length pair
-        struct vec_t result = { INT, final_states, &(*final_states)[0], final_states->size() };
+        struct vec_t result = { INT, final_states, final_states->data(), final_states->size() };
+        return result;
+    }
+
+    struct vec_t fsa_final_weights(const struct fsa_t *fsa){
+        std::vector<struct fsa_final> *final_weights = new std::vector<struct fsa_final>;
+        const fst::Fst<fst::StdArc> *fst = reinterpret(fsa);
+
+        // iterate through states, keep those with final weight ≠ zero
+        struct fsa_final final_weight;
+        for (fst::StateIterator<fst::StdFst> state(*fst); !state.Done(); state.Next()) {
+            if (fst->Final(state.Value()) != fst::TropicalWeight::Zero()){
+                final_weight.state = state.Value();
+                final_weight.weight = fst->Final(state.Value()).Value();
+                final_weights->push_back(final_weight);
+            }
+        }
+
+        struct vec_t result = { FINAL, final_weights, final_weights->data(), final_weights->size() };
+        return result;
+    }
+
+    struct fsa_t fst_from_arc_list( int states
+                                  , const struct vec_t *final_states
+                                  , const struct vec_t *arc_list){
+
+        fst::StdVectorFst mut;
+        fst_arc *arcs = static_cast<fst_arc*>(arc_list->first);
+        int *finals = static_cast<int*>(final_states->first);
+
+        for (int i = 0; i < states; i++){
+            mut.AddState();
+        }
+        for (size_t i = 0; i < arc_list->length; i++){
+            mut.AddArc(
+                arcs[i].from_state, fst::StdArc(arcs[i].ilabel, arcs[i].olabel, arcs[i].weight, arcs[i].to_state)
+            );
+        }
+        for (size_t i = 0; i < final_states->length; i++){
+            mut.SetFinal(finals[i], 0.0);
+        }
+        mut.SetStart(0);
+
+        fst::ArcSort(&mut, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(mut);
+    }
+
+    struct vec_t fst_to_arc_list(const struct fsa_t *wrapper){
+        std::vector<struct fst_arc> *vec = new std::vector<struct fst_arc>();
+        const fst::Fst<fst::StdArc> *fst = reinterpret(wrapper);
+
+        struct fst_arc carc;
+        for (fst::StateIterator<fst::StdFst> state(*fst); !state.Done(); state.Next()){
+            for (fst::ArcIterator<fst::StdFst> arc(*fst, state.Value()); !arc.Done(); arc.Next()){
+                carc.from_state = state.Value();
+                carc.to_state = arc.Value().nextstate;
+                carc.ilabel = arc.Value().ilabel;
+                carc.olabel = arc.Value().olabel;
+                carc.weight = arc.Value().weight.Value();
+
+                vec->push_back(carc);
+            }
+        }
+
+        struct vec_t al = { FST_ARC, vec, vec->data(), vec->size() };
+        return al;
+    }
+
+    struct fsa_t fst_relabel_input(const struct fsa_t *fst, const struct vec_t *label_map){
+        fst::StdVectorFst relabeled(*reinterpret(fst));
+        int *labels = static_cast<int*>(label_map->first);
+
+        // replace each input label l by labels[l], the output labels are kept
+        for (fst::StateIterator<fst::StdVectorFst> state(relabeled); !state.Done(); state.Next()){
+            for (fst::MutableArcIterator<fst::StdVectorFst> arc(&relabeled, state.Value()); !arc.Done(); arc.Next()){
+                fst::StdArc a = arc.Value();
+                a.ilabel = labels[a.ilabel];
+                arc.SetValue(a);
+            }
+        }
+        fst::ArcSort(&relabeled, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(relabeled);
+    }
+
+    struct fsa_t fst_compose(const struct fsa_t *a, const struct fsa_t *b){
+        // Compose expects the output labels of the first transducer to be sorted,
+        // the input labels of the second one are sorted after construction
+        fst::StdVectorFst sorted(*reinterpret(a));
+        fst::ArcSort(&sorted, fst::OLabelCompare<fst::StdArc>());
+
+        fst::StdVectorFst composition;
+        fst::Compose(sorted, *reinterpret(b), &composition);
+        fst::ArcSort(&composition, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(composition);
+    }
+
+    struct fsa_t fst_project(const struct fsa_t *fst, int output){
+        fst::StdVectorFst projected(*reinterpret(fst));
+        fst::Project(&projected, output ? fst::PROJECT_OUTPUT : fst::PROJECT_INPUT);
+        fst::ArcSort(&projected, fst::ILabelCompare<fst::StdArc>());
+
+        return freeze(projected);
+    }
+
+    struct vec_t fsa_shortest_distance(const struct fsa_t *fsa, int reverse){
+        // compute the distances in the log semiring, s.t. the weights of all runs are summed up
+        fst::VectorFst<fst::LogArc> log_fsa;
+        fst::ArcMap(*reinterpret(fsa), &log_fsa, fst::WeightConvertMapper<fst::StdArc, fst::LogArc>());
+        std::vector<fst::LogWeight> distance;
+        fst::ShortestDistance(log_fsa, &distance, reverse != 0);
+
+        // states that are not reached are missing at the end of distance
+        std::vector<float> *distances = new std::vector<float>(log_fsa.NumStates(), fst::LogWeight::Zero().Value());
+        for (size_t state = 0; state < distance.size() && state < distances->size(); state++){
+            (*distances)[state] = distance[state].Value();
+        }
+
+        struct vec_t result = { FLOAT, distances, distances->data(), distances->size() };
         return result;
     }
 
@@ -177,6 +751,15 @@ extern "C" {
             case ARC:
                 delete static_cast<std::vector<fsa_arc>*>(vec->vec_obj);
                 return;
+            case FINAL:
+                delete static_cast<std::vector<fsa_final>*>(vec->vec_obj);
+                return;
+            case FST_ARC:
+                delete static_cast<std::vector<fst_arc>*>(vec->vec_obj);
+                return;
+            case FLOAT:
+                delete static_cast<std::vector<float>*>(vec->vec_obj);
+                return;
         }
     }
 
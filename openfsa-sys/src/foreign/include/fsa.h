@@ -10,7 +10,11 @@ enum fsa_type {
 enum vec_type {
     CHAR,
     INT,
-    ARC
+    ARC,
+    FINAL,
+    FST_ARC,
+    FLOAT,
+    FSA
 };
 
 struct fsa_t {
@@ -18,11 +22,25 @@ struct fsa_t {
     void *fsa;
 };
 
+struct encoder_t {
+    void *mapper;
+};
+
 struct fsa_arc {
     int from_state, to_state, label;
     float weight;
 };
 
+struct fst_arc {
+    int from_state, to_state, ilabel, olabel;
+    float weight;
+};
+
+struct fsa_final {
+    int state;
+    float weight;
+};
+
 struct vec_t {
     unsigned char type;
     void *vec_obj, *first;
@@ -38,16 +56,58 @@ extern "C" {
 
     struct fsa_t fsa_from_arc_list(int states, const struct vec_t *final_states,  const struct vec_t *arclist);
     struct vec_t fsa_to_arc_list(const struct fsa_t *fsa);
+    struct vec_t fsa_arcs_from(const struct fsa_t *fsa, int state);
 
     int fsa_initial_state(const struct fsa_t *fsa);
+    int fsa_num_states(const struct fsa_t *fsa);
+    int fsa_num_arcs(const struct fsa_t *fsa);
+    int fsa_is_empty(const struct fsa_t *fsa);
+    int fsa_equivalent(const struct fsa_t *a, const struct fsa_t *b);
+    unsigned long long fsa_count_paths(const struct fsa_t *fsa);
+    unsigned long long fsa_properties(const struct fsa_t *fsa, unsigned long long mask);
+    float fsa_weight_of(const struct fsa_t *fsa, const struct vec_t *word);
+    int fsa_is_final(const struct fsa_t *fsa, int state);
     struct vec_t fsa_final_states(const struct fsa_t *fsa);
+    struct vec_t fsa_final_weights(const struct fsa_t *fsa);
+    struct vec_t fsa_shortest_distance(const struct fsa_t *fsa, int reverse);
 
-    struct fsa_t fsa_n_best(const struct fsa_t *fsa, int n);
+    struct fsa_t fsa_n_best(const struct fsa_t *fsa, int n, int unique);
     struct fsa_t fsa_intersect(const struct fsa_t *a, const struct fsa_t *b);
     struct fsa_t fsa_difference(const struct fsa_t *a, const struct fsa_t *b);
+    struct fsa_t fsa_union(const struct fsa_t *a, const struct fsa_t *b);
+    struct fsa_t fsa_concat(const struct fsa_t *a, const struct fsa_t *b);
+    struct fsa_t fsa_concat_all(const struct vec_t *fsas);
+    struct fsa_t fsa_union_all(const struct vec_t *fsas);
+    struct fsa_t fsa_closure(const struct fsa_t *fsa, int plus);
+    struct fsa_t fsa_reverse(const struct fsa_t *fsa);
+    struct fsa_t fsa_determinize(const struct fsa_t *fsa);
+    struct fsa_t fsa_minimize(const struct fsa_t *fsa);
+    struct fsa_t fsa_remove_epsilon(const struct fsa_t *fsa);
+    struct fsa_t fsa_connect(const struct fsa_t *fsa);
+    struct fsa_t fsa_prune(const struct fsa_t *fsa, float threshold);
+    struct fsa_t fsa_retain_arcs(const struct fsa_t *fsa, const struct vec_t *keep);
+    struct fsa_t fsa_reweight(const struct fsa_t *fsa, const struct vec_t *arc_weights, const struct vec_t *final_weights);
+    struct fsa_t fsa_push(const struct fsa_t *fsa, int to_final);
+    struct fsa_t fsa_normalize(const struct fsa_t *fsa);
+    struct fsa_t fsa_arc_sort(const struct fsa_t *fsa, int by_state);
+    struct fsa_t fsa_topsort(const struct fsa_t *fsa);
+
+    struct fsa_t fsa_relabel(const struct fsa_t *fsa, const struct vec_t *label_map);
+
+    struct encoder_t encoder_new();
+    int encoder_size(const struct encoder_t *encoder);
+    struct fsa_t fsa_encode(const struct fsa_t *fsa, struct encoder_t *encoder);
+    struct fsa_t fsa_decode(const struct fsa_t *fsa, const struct encoder_t *encoder);
+
+    struct fsa_t fst_from_arc_list(int states, const struct vec_t *final_states, const struct vec_t *arclist);
+    struct vec_t fst_to_arc_list(const struct fsa_t *fst);
+    struct fsa_t fst_relabel_input(const struct fsa_t *fst, const struct vec_t *label_map);
+    struct fsa_t fst_compose(const struct fsa_t *a, const struct fsa_t *b);
+    struct fsa_t fst_project(const struct fsa_t *fst, int output);
 
     void fsa_free(const struct fsa_t *fsa);
     void vec_free(const struct vec_t *vec);
+    void encoder_free(const struct encoder_t *encoder);
 
 #ifdef __cplusplus
 }
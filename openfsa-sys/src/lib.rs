@@ -1,5 +1,7 @@
 extern crate libc;
-use libc::{c_char, c_float, c_int, c_uchar, c_void};
+use libc::{c_char, c_float, c_int, c_uchar, c_ulonglong, c_void};
+use std::marker::PhantomData;
+use std::ops::Deref;
 use std::ptr;
 use std::slice::from_raw_parts;
 
@@ -8,6 +10,7 @@ use std::fmt::{Debug, Error, Formatter};
 extern crate serde;
 use serde::ser::{Serialize, Serializer};
 use serde::de::{Deserialize, Deserializer};
+use serde::de::Error as DeError;
 
 /// Wrapper type for a pointer to an FSA object in `OpenFst`.
 #[repr(C)]
@@ -16,6 +19,13 @@ pub struct fsa_t {
     fsa: *mut c_void,
 }
 
+/// Wrapper type for a pointer to a table in `OpenFst` that encodes pairs of
+/// labels and weights as single labels.
+#[repr(C)]
+pub struct encoder_t {
+    mapper: *mut c_void,
+}
+
 /// An integerized `Arc` with logarithmic pobabilistic weight.
 #[derive(PartialEq, Debug, Clone)]
 #[repr(C)]
@@ -26,6 +36,25 @@ pub struct fsa_arc {
     pub weight: c_float,
 }
 
+/// An integerized transition of a transducer with an input and an output label.
+#[derive(PartialEq, Debug, Clone)]
+#[repr(C)]
+pub struct fst_arc {
+    pub from_state: c_int,
+    pub to_state: c_int,
+    pub ilabel: c_int,
+    pub olabel: c_int,
+    pub weight: c_float,
+}
+
+/// A final state with logarithmic probabilistic weight.
+#[derive(PartialEq, Debug, Clone)]
+#[repr(C)]
+pub struct fsa_final {
+    pub state: c_int,
+    pub weight: c_float,
+}
+
 /// Vector type for calls between C++, C and Rust.
 #[repr(C)]
 pub struct vec_t {
@@ -35,6 +64,34 @@ pub struct vec_t {
     length: usize,
 }
 
+/// Properties of an FSA as defined in 'fst/properties.h' of OpenFst.
+/// Each property is a bit of the value returned by `fsa_properties`.
+pub mod properties {
+    use libc::c_ulonglong;
+
+    /// Each state has at most one outgoing arc per label.
+    pub const I_DETERMINISTIC: c_ulonglong = 0x0000_0004_0000;
+    /// The FSA has no epsilon transitions.
+    pub const NO_EPSILONS: c_ulonglong = 0x0000_0080_0000;
+    /// The arcs leaving each state are sorted by their labels.
+    pub const I_LABEL_SORTED: c_ulonglong = 0x0000_1000_0000;
+    /// Some arc or final state has a weight other than one and zero.
+    pub const WEIGHTED: c_ulonglong = 0x0001_0000_0000;
+    /// The FSA has cycles.
+    pub const CYCLIC: c_ulonglong = 0x0004_0000_0000;
+    /// The FSA has no cycles.
+    pub const ACYCLIC: c_ulonglong = 0x0008_0000_0000;
+    /// Each arc leads from a state to a greater one.
+    pub const TOP_SORTED: c_ulonglong = 0x0040_0000_0000;
+    /// Each state is reachable from the initial state.
+    pub const ACCESSIBLE: c_ulonglong = 0x0100_0000_0000;
+    /// A final state is reachable from each state.
+    pub const CO_ACCESSIBLE: c_ulonglong = 0x0400_0000_0000;
+}
+
+/// Result of `fsa_count_paths` for an FSA whose runs cannot be counted.
+pub const COUNT_OVERFLOW: c_ulonglong = c_ulonglong::MAX;
+
 // c function wrappers for 'foreign/fsa.cpp'
 #[link(name = "fsa")]
 #[link(name = "fst")]
@@ -43,71 +100,324 @@ extern "C" {
     /// Encodes an FSA into a binary string.
     pub fn fsa_to_string(fsa: *const fsa_t) -> vec_t;
     /// Decodes an FSA from a binary string.
+    /// The FSA's pointer is null if the binary string is malformed.
     pub fn fsa_from_string(binary: *const vec_t) -> fsa_t;
 
     /// Creates a new FSA from
     /// * the numer of states,
-    /// * a list of accepting states, and
+    /// * a list of accepting states with their weights, and
     /// * a list of arcs.
     pub fn fsa_from_arc_list(
         states: c_int,
-        final_stats: *const vec_t,
+        final_states: *const vec_t,
         arc_list: *const vec_t,
     ) -> fsa_t;
     /// Returns the list of all arcs of an FSA.
     pub fn fsa_to_arc_list(fsa: *const fsa_t) -> vec_t;
+    /// Returns the list of arcs leaving a state of an FSA,
+    /// which is empty if the state does not exist.
+    pub fn fsa_arcs_from(fsa: *const fsa_t, state: c_int) -> vec_t;
 
     /// Returns the initial state of an FSA.
     pub fn fsa_initial_state(fsa: *const fsa_t) -> c_int;
+    /// Returns the number of states of an FSA.
+    pub fn fsa_num_states(fsa: *const fsa_t) -> c_int;
+    /// Returns the number of arcs of an FSA.
+    pub fn fsa_num_arcs(fsa: *const fsa_t) -> c_int;
+    /// Returns a non-zero value iff no final state of an FSA is reachable from its
+    /// initial state.
+    pub fn fsa_is_empty(fsa: *const fsa_t) -> c_int;
+    /// Returns a non-zero value iff two FSA recognize the same weighted language,
+    /// where the weights of equivalent runs are summed up.
+    pub fn fsa_equivalent(a: *const fsa_t, b: *const fsa_t) -> c_int;
+    /// Returns the number of accepting runs of an FSA, or `COUNT_OVERFLOW` if it
+    /// contains a cycle or if there are at least `COUNT_OVERFLOW` runs.
+    pub fn fsa_count_paths(fsa: *const fsa_t) -> c_ulonglong;
+    /// Returns the properties of an FSA that are selected by a mask of `properties`.
+    pub fn fsa_properties(fsa: *const fsa_t, mask: c_ulonglong) -> c_ulonglong;
+    /// Returns the sum of the weights of all accepting runs of an FSA on a list of labels,
+    /// the weight is infinite if there is no such run.
+    pub fn fsa_weight_of(fsa: *const fsa_t, word: *const vec_t) -> c_float;
+    /// Returns a non-zero value iff a state of an FSA has a final weight other than zero.
+    /// States that do not exist are not final.
+    pub fn fsa_is_final(fsa: *const fsa_t, state: c_int) -> c_int;
     /// Returns the list of final states of an FSA.
     pub fn fsa_final_states(fsa: *const fsa_t) -> vec_t;
+    /// Returns the list of final states of an FSA with their weights.
+    pub fn fsa_final_weights(fsa: *const fsa_t) -> vec_t;
+    /// Returns the sums of the weights of all runs from the initial state to each state
+    /// of an FSA, or from each state to the final states if `reverse` is non-zero.
+    /// The list is indexed by states.
+    pub fn fsa_shortest_distance(fsa: *const fsa_t, reverse: c_int) -> vec_t;
 
     /// Creates the n-best FSA that contains the n best runs of an FSA.
-    pub fn fsa_n_best(fsa: *const fsa_t, n: c_int) -> fsa_t;
+    /// If `unique` is non-zero, it contains the best runs of the n best distinct words.
+    pub fn fsa_n_best(fsa: *const fsa_t, n: c_int, unique: c_int) -> fsa_t;
     /// Constructs the product of two FSA.
+    /// The resulting FSA's pointer is null if one of the FSA is null or the
+    /// operation failed.
     pub fn fsa_intersect(a: *const fsa_t, b: *const fsa_t) -> fsa_t;
     /// Constructs the product of an FSA with the inverse of a second FSA.
+    /// The resulting FSA's pointer is null if one of the FSA is null or the
+    /// operation failed.
     pub fn fsa_difference(a: *const fsa_t, b: *const fsa_t) -> fsa_t;
+    /// Constructs an FSA whose language is the union of the languages of two FSA.
+    pub fn fsa_union(a: *const fsa_t, b: *const fsa_t) -> fsa_t;
+    /// Constructs an FSA whose language contains the words of an FSA followed by the words of
+    /// a second FSA.
+    pub fn fsa_concat(a: *const fsa_t, b: *const fsa_t) -> fsa_t;
+    /// Constructs an FSA whose language contains the concatenations of the words of
+    /// a list of pointers to FSAs; all FSAs are concatenated in a single pass.
+    pub fn fsa_concat_all(fsas: *const vec_t) -> fsa_t;
+    /// Constructs an FSA whose language contains the words of a list of pointers to FSAs
+    /// using a single new initial state.
+    pub fn fsa_union_all(fsas: *const vec_t) -> fsa_t;
+    /// Constructs the Kleene closure of an FSA, i.e. the plus closure if `plus` is non-zero
+    /// and the star closure otherwise.
+    pub fn fsa_closure(fsa: *const fsa_t, plus: c_int) -> fsa_t;
+    /// Constructs an FSA that recognizes the reversed words of an FSA.
+    pub fn fsa_reverse(fsa: *const fsa_t) -> fsa_t;
+    /// Constructs a deterministic FSA that recognizes the same weighted language as an FSA,
+    /// the weights of equivalent runs are summed up.
+    pub fn fsa_determinize(fsa: *const fsa_t) -> fsa_t;
+    /// Constructs a minimal FSA that recognizes the same weighted language as a
+    /// deterministic FSA.
+    pub fn fsa_minimize(fsa: *const fsa_t) -> fsa_t;
+    /// Constructs an FSA without epsilon transitions that recognizes the same weighted
+    /// language as an FSA.
+    pub fn fsa_remove_epsilon(fsa: *const fsa_t) -> fsa_t;
+    /// Constructs an FSA without the states of an FSA that are not on any accepting path.
+    pub fn fsa_connect(fsa: *const fsa_t) -> fsa_t;
+    /// Constructs an FSA without the arcs and states of an FSA that are not on any
+    /// accepting path whose cost exceeds the cost of the best path by at most `threshold`.
+    pub fn fsa_prune(fsa: *const fsa_t, threshold: c_float) -> fsa_t;
+    /// Constructs an FSA with the arcs of an FSA whose entries in a list of integers are
+    /// non-zero, where the list is in the order of `fsa_to_arc_list`.
+    pub fn fsa_retain_arcs(fsa: *const fsa_t, keep: *const vec_t) -> fsa_t;
+    /// Constructs an FSA whose arcs have the weights of a list of weights in the order of
+    /// `fsa_to_arc_list`, and whose final states are set to a list of `fsa_final`.
+    pub fn fsa_reweight(
+        fsa: *const fsa_t,
+        arc_weights: *const vec_t,
+        final_weights: *const vec_t,
+    ) -> fsa_t;
+    /// Constructs an FSA that recognizes the same weighted language as an FSA, whose weights
+    /// are pushed towards the final states if `to_final` is non-zero and towards the
+    /// initial state otherwise.
+    pub fn fsa_push(fsa: *const fsa_t, to_final: c_int) -> fsa_t;
+    /// Constructs an FSA whose weights are pushed towards the initial state like `fsa_push`
+    /// and divided by the total weight of the language of an FSA.
+    pub fn fsa_normalize(fsa: *const fsa_t) -> fsa_t;
+    /// Constructs an FSA whose arcs leaving each state are sorted by their target states
+    /// if `by_state` is non-zero and by their labels otherwise.
+    pub fn fsa_arc_sort(fsa: *const fsa_t, by_state: c_int) -> fsa_t;
+    /// Constructs an FSA whose states are numbered in topological order;
+    /// the result is NULL if the FSA is cyclic.
+    pub fn fsa_topsort(fsa: *const fsa_t) -> fsa_t;
+
+    /// Replaces each label `l` of an FSA by the `l`-th entry of a list of labels.
+    pub fn fsa_relabel(fsa: *const fsa_t, label_map: *const vec_t) -> fsa_t;
+
+    /// Creates an empty table for encoding pairs of labels and weights.
+    pub fn encoder_new() -> encoder_t;
+    /// Returns the number of pairs of labels and weights in a table.
+    pub fn encoder_size(encoder: *const encoder_t) -> c_int;
+    /// Replaces the label and weight of each transition and final state of an FSA by
+    /// a single label, such that all weights are one; new pairs are added to the table.
+    pub fn fsa_encode(fsa: *const fsa_t, encoder: *mut encoder_t) -> fsa_t;
+    /// Replaces each encoded label of an FSA by its pair of label and weight.
+    /// Returns a null pointer if a label is not in the table.
+    pub fn fsa_decode(fsa: *const fsa_t, encoder: *const encoder_t) -> fsa_t;
+
+    /// Creates a new transducer from
+    /// * the numer of states,
+    /// * a list of accepting states, and
+    /// * a list of transitions with input and output labels.
+    ///
+    /// The transducer is wrapped in an `fsa_t` like an FSA.
+    pub fn fst_from_arc_list(
+        states: c_int,
+        final_states: *const vec_t,
+        arc_list: *const vec_t,
+    ) -> fsa_t;
+    /// Returns the list of all transitions of a transducer.
+    pub fn fst_to_arc_list(fst: *const fsa_t) -> vec_t;
+    /// Replaces each input label `l` of a transducer by the `l`-th entry of a list of labels.
+    pub fn fst_relabel_input(fst: *const fsa_t, label_map: *const vec_t) -> fsa_t;
+    /// Constructs the composition of two transducers, i.e. the output labels of the first
+    /// transducer are matched with the input labels of the second one.
+    pub fn fst_compose(a: *const fsa_t, b: *const fsa_t) -> fsa_t;
+    /// Constructs the FSA that recognizes the output words of a transducer if `output`
+    /// is non-zero and its input words otherwise.
+    pub fn fst_project(fst: *const fsa_t, output: c_int) -> fsa_t;
 
     /// Frees the object.
     pub fn fsa_free(fsa: *const fsa_t);
     /// Frees the object.
     pub fn vec_free(vec: *const vec_t);
+    /// Frees the object.
+    pub fn encoder_free(encoder: *const encoder_t);
 }
 
+// An `fsa_t` either owns its FSA object or its pointer is null, e.g. after decoding
+// a malformed binary string; only owned objects are freed.
 impl Drop for fsa_t {
+    fn drop(&mut self) {
+        if !self.is_null() {
+            unsafe {
+                fsa_free(self);
+            };
+        }
+    }
+}
+
+// The FSA objects are never mutated after their construction in 'foreign/fsa.cpp'.
+// They are stored as `ConstFst`, which does not cache and is thread-safe for reading in OpenFst.
+// Hence, an `fsa_t` can be sent to and shared among threads; it is only freed on `drop`.
+unsafe impl Send for fsa_t {}
+unsafe impl Sync for fsa_t {}
+
+impl Drop for encoder_t {
     fn drop(&mut self) {
         unsafe {
-            fsa_free(self);
+            encoder_free(self);
         };
     }
 }
 
+// A table is only mutated by `fsa_encode`, which requires a mutable reference.
+unsafe impl Send for encoder_t {}
+unsafe impl Sync for encoder_t {}
+
+impl fsa_t {
+    /// Checks if the pointer to the FSA object is null, e.g. after decoding
+    /// a malformed binary string.
+    pub fn is_null(&self) -> bool {
+        self.fsa.is_null()
+    }
+}
+
+/// Types of the elements of a `vec_t`.
+/// Each type is identified by the tag that is stored in a `vec_t` as
+/// defined by `vec_type` in 'foreign/include/fsa.h'.
+pub trait VecElem {
+    const TAG: c_uchar;
+}
+
+impl VecElem for i8 {
+    const TAG: c_uchar = 0;
+}
+
+impl VecElem for u8 {
+    const TAG: c_uchar = 0;
+}
+
+impl VecElem for c_int {
+    const TAG: c_uchar = 1;
+}
+
+impl VecElem for fsa_arc {
+    const TAG: c_uchar = 2;
+}
+
+impl VecElem for fsa_final {
+    const TAG: c_uchar = 3;
+}
+
+impl VecElem for fst_arc {
+    const TAG: c_uchar = 4;
+}
+
+impl VecElem for c_float {
+    const TAG: c_uchar = 5;
+}
+
+impl VecElem for *const fsa_t {
+    const TAG: c_uchar = 6;
+}
+
+/// A `vec_t` referencing to the slice of a `Vec` that it borrows.
+/// The borrow ensures that the `Vec` outlives the `vec_t`:
+///
+/// ```compile_fail
+/// extern crate openfsa_sys;
+/// use openfsa_sys::vec_t;
+///
+/// let mut vector: Vec<i32> = vec![1, 2, 3];
+/// let cvec = vec_t::new(&mut vector);
+/// drop(vector);
+/// cvec.as_slice::<i32>();
+/// ```
+pub struct VecRef<'a, T: 'a> {
+    vec: vec_t,
+    vector: PhantomData<&'a mut Vec<T>>,
+}
+
+impl<'a, T> Deref for VecRef<'a, T> {
+    type Target = vec_t;
+
+    fn deref(&self) -> &vec_t {
+        &self.vec
+    }
+}
+
 impl vec_t {
     /// Creates a new `vec_t` referencing to the slice owned by `vector`-
-    pub fn new<T>(vector: &mut Vec<T>) -> Self {
+    /// It borrows `vector` as long as it is used.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<'a, T: VecElem>(vector: &'a mut Vec<T>) -> VecRef<'a, T> {
         vector.shrink_to_fit();
-        vec_t {
-            inner_type: 255,
-            length: vector.len(),
-            first: vector.as_mut_ptr() as *mut c_void,
-            vec_obj: ptr::null_mut(),
+        VecRef {
+            vec: vec_t {
+                inner_type: T::TAG,
+                length: vector.len(),
+                first: vector.as_mut_ptr() as *mut c_void,
+                vec_obj: ptr::null_mut(),
+            },
+            vector: PhantomData,
         }
     }
 
     /// Borrow the slice referenced by a `vec_t`-
-    pub fn as_slice<T>(&self) -> &[T] {
+    /// Panics in debug builds if the elements are not of type `T`.
+    pub fn as_slice<T: VecElem>(&self) -> &[T] {
+        debug_assert!(
+            self.inner_type == T::TAG,
+            "vec_t contains elements with tag {}, but they were read as elements with tag {}",
+            self.inner_type,
+            T::TAG
+        );
+        // the first element of an empty vector may be a null pointer
+        if self.length == 0 {
+            return &[];
+        }
         unsafe { from_raw_parts(self.first as *mut T, self.length) }
     }
 
     /// Clones all values in the slice referenced by a `vec_t`.
+    /// Panics in debug builds if the elements are not of type `T`.
     pub fn to_vec<T>(&self) -> Vec<T>
     where
-        T: Clone,
+        T: VecElem + Clone,
     {
         let slice = self.as_slice();
         slice.to_vec()
     }
+
+    /// Number of elements referenced by a `vec_t`.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Checks if a `vec_t` references no elements.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Tag of the type of the elements referenced by a `vec_t`, see `VecElem`.
+    pub fn element_tag(&self) -> u8 {
+        self.inner_type
+    }
 }
 
 impl Drop for vec_t {
@@ -122,9 +432,18 @@ impl Drop for vec_t {
 
 impl Debug for fsa_t {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        let cvec = unsafe { fsa_to_string(self) };
-        let vr: Vec<c_char> = cvec.to_vec();
-        write!(f, "{:?}", vr)
+        if self.is_null() {
+            return write!(f, "<null fsa>");
+        }
+
+        let (states, arcs, initial) = unsafe {
+            (
+                fsa_num_states(self),
+                fsa_to_arc_list(self).as_slice::<fsa_arc>().len(),
+                fsa_initial_state(self),
+            )
+        };
+        write!(f, "fsa_t {{ states={}, arcs={}, initial={} }}", states, arcs, initial)
     }
 }
 
@@ -142,8 +461,13 @@ impl Serialize for fsa_t {
 impl<'de> Deserialize<'de> for fsa_t {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<fsa_t, D::Error> {
         let mut bytes = Vec::<c_char>::deserialize(deserializer)?;
+        let fsa = unsafe { fsa_from_string(&*vec_t::new(&mut bytes)) };
 
-        Ok(unsafe { fsa_from_string(&vec_t::new(&mut bytes)) })
+        if fsa.is_null() {
+            Err(D::Error::custom("malformed binary string of an fsa"))
+        } else {
+            Ok(fsa)
+        }
     }
 }
 
@@ -162,10 +486,15 @@ mod tests {
                 weight: 1.0 as c_float,
             },
         ];
-        let mut finals = vec![0 as c_int];
+        let mut finals = vec![
+            fsa_final {
+                state: 0 as c_int,
+                weight: 0.0 as c_float,
+            },
+        ];
         let arcs_: Vec<fsa_arc> = unsafe {
             let fsa =
-                fsa_from_arc_list(1 as c_int, &vec_t::new(&mut finals), &vec_t::new(&mut arcs));
+                fsa_from_arc_list(1 as c_int, &*vec_t::new(&mut finals), &*vec_t::new(&mut arcs));
             let alist = fsa_to_arc_list(&fsa);
             let a_slice: &[fsa_arc] = alist.as_slice();
             a_slice.iter().map(|a| a.clone()).collect()
@@ -173,4 +502,109 @@ mod tests {
 
         assert_eq!(arcs, arcs_);
     }
+
+    #[test]
+    fn typed_vectors() {
+        let mut arcs = vec![
+            fsa_arc {
+                from_state: 0 as c_int,
+                to_state: 1 as c_int,
+                label: 1 as c_int,
+                weight: 1.0 as c_float,
+            },
+        ];
+        let mut finals = vec![
+            fsa_final {
+                state: 1 as c_int,
+                weight: 0.5 as c_float,
+            },
+        ];
+        let (arcs_, states, finals_): (Vec<fsa_arc>, Vec<c_int>, Vec<fsa_final>) = unsafe {
+            let fsa =
+                fsa_from_arc_list(2 as c_int, &*vec_t::new(&mut finals), &*vec_t::new(&mut arcs));
+            (
+                fsa_to_arc_list(&fsa).to_vec(),
+                fsa_final_states(&fsa).to_vec(),
+                fsa_final_weights(&fsa).to_vec(),
+            )
+        };
+
+        assert_eq!(arcs, arcs_);
+        assert_eq!(vec![1 as c_int], states);
+        assert_eq!(finals, finals_);
+    }
+
+    #[test]
+    fn debug_summary() {
+        let mut arcs = vec![
+            fsa_arc {
+                from_state: 0 as c_int,
+                to_state: 1 as c_int,
+                label: 1 as c_int,
+                weight: 1.0 as c_float,
+            },
+        ];
+        let mut finals = vec![
+            fsa_final {
+                state: 1 as c_int,
+                weight: 0.0 as c_float,
+            },
+        ];
+        let fsa = unsafe {
+            fsa_from_arc_list(2 as c_int, &*vec_t::new(&mut finals), &*vec_t::new(&mut arcs))
+        };
+
+        let summary = format!("{:?}", fsa);
+        assert!(summary.contains("states=2"));
+        assert!(summary.contains("arcs=1"));
+        assert!(summary.contains("initial=0"));
+
+        let null = unsafe { fsa_from_string(&*vec_t::new(&mut vec![0u8; 8])) };
+        assert_eq!("<null fsa>", format!("{:?}", null));
+    }
+
+    #[test]
+    fn vector_diagnostics() {
+        assert_eq!(3, vec_t::new(&mut vec![1i32, 2, 3]).len());
+        assert_eq!(<c_int as VecElem>::TAG, vec_t::new(&mut vec![1i32, 2, 3]).element_tag());
+
+        let mut floats: Vec<c_float> = Vec::new();
+        let empty = vec_t::new(&mut floats);
+        assert!(empty.is_empty());
+        assert_eq!(<c_float as VecElem>::TAG, empty.element_tag());
+    }
+
+    #[test]
+    fn null_drop() {
+        for bytes in &mut [Vec::new(), vec![0u8; 8], vec![255u8; 64]] {
+            let fsa = unsafe { fsa_from_string(&*vec_t::new(bytes)) };
+            assert!(fsa.is_null());
+            drop(fsa);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn mismatched_vector_type() {
+        let mut arcs = vec![
+            fsa_arc {
+                from_state: 0 as c_int,
+                to_state: 0 as c_int,
+                label: 1 as c_int,
+                weight: 1.0 as c_float,
+            },
+        ];
+        let mut finals = vec![
+            fsa_final {
+                state: 0 as c_int,
+                weight: 0.0 as c_float,
+            },
+        ];
+        unsafe {
+            let fsa =
+                fsa_from_arc_list(1 as c_int, &*vec_t::new(&mut finals), &*vec_t::new(&mut arcs));
+            fsa_to_arc_list(&fsa).as_slice::<c_int>();
+        }
+    }
 }
@@ -0,0 +1,155 @@
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync;
+use openfsa_sys::*;
+use integeriser::{HashIntegeriser, Integeriser};
+use log_domain::LogDomain;
+
+use fsa::{checked_id, Arc, Automaton, Label, Semiring};
+
+/// Incremental construction of an `Automaton` with states of type `Q`
+/// and labels of type `A`.
+/// In contrast to `Automaton::from_arcs`, the transitions are integerized when
+/// they are added, the FSA is only constructed once by `build`.
+pub struct AutomatonBuilder<Q, A, W = LogDomain<f32>>
+where
+    Q: Hash + Eq,
+    A: Hash + Eq,
+{
+    i_states: HashIntegeriser<Q>,
+    i_labels: HashIntegeriser<A>,
+    initial_state: Option<usize>,
+    final_states: Vec<fsa_final>,
+    arcs: Vec<fsa_arc>,
+    weights: PhantomData<W>,
+}
+
+impl<Q, A, W> AutomatonBuilder<Q, A, W>
+where
+    Q: Hash + Eq + Clone,
+    A: Hash + Eq + Clone,
+    W: Semiring,
+{
+    /// Initializes an `AutomatonBuilder` without states and transitions.
+    pub fn new() -> Self {
+        AutomatonBuilder {
+            i_states: HashIntegeriser::new(),
+            i_labels: HashIntegeriser::new(),
+            initial_state: None,
+            final_states: Vec::new(),
+            arcs: Vec::new(),
+            weights: PhantomData,
+        }
+    }
+
+    /// Adds a transition whose label is either a symbol or `Label::Epsilon`.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn add_arc<L>(&mut self, arc: Arc<Q, L, W>) -> &mut Self
+    where
+        L: Into<Label<A>>,
+    {
+        let carc = Automaton::integerise_arc(arc, &mut self.i_states, &mut self.i_labels).unwrap();
+        self.arcs.push(carc);
+        self
+    }
+
+    /// Sets the initial state, replacing the one that was set before.
+    pub fn set_initial(&mut self, state: Q) -> &mut Self {
+        self.initial_state = Some(self.i_states.integerise(state));
+        self
+    }
+
+    /// Adds a final state with weight one.
+    /// Panics if there are more states than integers in OpenFst.
+    pub fn add_final(&mut self, state: Q) -> &mut Self {
+        let final_state = fsa_final {
+            state: checked_id(self.i_states.integerise(state)).unwrap(),
+            weight: W::one().to_openfst(),
+        };
+        self.final_states.push(final_state);
+        self
+    }
+
+    /// Constructs the `Automaton`.
+    /// If no initial state was set, the first state that was added is the initial state.
+    /// Panics if there are more states than integers in OpenFst.
+    pub fn build(self) -> Automaton<A, W> {
+        let AutomatonBuilder {
+            i_states,
+            i_labels,
+            initial_state,
+            mut final_states,
+            mut arcs,
+            ..
+        } = self;
+
+        // OpenFst starts in state 0, so it is swapped with the initial state
+        let q0 = checked_id(initial_state.unwrap_or(0)).unwrap();
+        let swap = |q| if q == q0 { 0 } else if q == 0 { q0 } else { q };
+        for carc in &mut arcs {
+            carc.from_state = swap(carc.from_state);
+            carc.to_state = swap(carc.to_state);
+        }
+        for qf in &mut final_states {
+            qf.state = swap(qf.state);
+        }
+
+        // the language of an FSA without states is empty
+        let states = if i_states.size() == 0 { 1 } else { i_states.size() };
+        let fsa = unsafe {
+            fsa_from_arc_list(
+                checked_id(states).unwrap(),
+                &*vec_t::new(&mut final_states),
+                &*vec_t::new(&mut arcs),
+            )
+        };
+
+        Automaton::from_fsa(fsa, sync::Arc::new(i_labels))
+    }
+}
+
+impl<Q, A, W> Default for AutomatonBuilder<Q, A, W>
+where
+    Q: Hash + Eq + Clone,
+    A: Hash + Eq + Clone,
+    W: Semiring,
+{
+    fn default() -> Self {
+        AutomatonBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fsa::{Arc, Automaton};
+    use log_domain::LogDomain;
+    use num_traits::One;
+    use super::AutomatonBuilder;
+
+    #[test]
+    fn incremental_construction() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs.clone());
+
+        let mut builder = AutomatonBuilder::new();
+        for arc in arcs.into_iter().rev() {
+            builder.add_arc(arc);
+        }
+        builder.set_initial("q1").add_final("q1");
+
+        assert_eq!(fsa.into_arcs(), builder.build().into_arcs());
+    }
+}
@@ -0,0 +1,109 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync;
+use openfsa_sys::*;
+use integeriser::{HashIntegeriser, Integeriser};
+use log_domain::LogDomain;
+
+use fsa::{Automaton, FsaError, Semiring};
+
+/// Table of the pairs of labels and weights that `Automaton::encode` replaced by
+/// single labels.
+/// An encoded `Automaton` is labeled by the positions of the pairs in this table and all
+/// of its weights are one, so it can be determinized or minimized like an unweighted
+/// `Automaton`; `decode` restores the labels and weights afterwards.
+pub struct EncodeTable<A, W = LogDomain<f32>>
+where
+    A: Hash + Eq,
+{
+    encoder: encoder_t,
+    // labels of the decoded Automata
+    labels: sync::Arc<HashIntegeriser<A>>,
+    // labels of the encoded Automata, i.e. the positions of the pairs
+    positions: HashIntegeriser<usize>,
+    weights: PhantomData<W>,
+}
+
+impl<A, W> EncodeTable<A, W>
+where
+    A: Hash + Eq + Clone,
+    W: Semiring,
+{
+    // encodes an Automaton using a new table
+    pub(crate) fn encode(fsa: &Automaton<A, W>) -> (Automaton<usize, W>, Self) {
+        let mut encoder = unsafe { encoder_new() };
+        let encoded = unsafe { fsa_encode(fsa.fsa.borrow(), &mut encoder) };
+
+        // OpenFst numbers the pairs starting with 1, zero is reserved for epsilon
+        let mut positions = HashIntegeriser::new();
+        for position in 0..(unsafe { encoder_size(&encoder) } as usize) {
+            positions.integerise(position);
+        }
+
+        let encoded = Automaton::from_fsa(encoded, sync::Arc::new(positions.clone()));
+        let table = EncodeTable {
+            encoder,
+            labels: sync::Arc::clone(&fsa.labels),
+            positions,
+            weights: PhantomData,
+        };
+        (encoded, table)
+    }
+
+    /// Restores the labels and weights of an `Automaton` that was encoded with an
+    /// `EncodeTable`, e.g. after it was determinized.
+    /// Fails if a label of the `Automaton` is not in the table.
+    pub fn decode(&self, encoded: &Automaton<usize, W>) -> Result<Automaton<A, W>, FsaError> {
+        // the positions may be integerized differently after binary operations
+        let encoded = encoded.relabel(&self.positions);
+        let fsa = unsafe { fsa_decode(encoded.fsa.borrow(), &self.encoder) };
+        Automaton::checked(fsa, sync::Arc::clone(&self.labels), "decoding")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fsa::{Arc, Automaton};
+    use log_domain::LogDomain;
+    use num_traits::One;
+
+    #[test]
+    fn encoded_determinization() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.3).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::new(0.2).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q4",
+                label: "b",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q3",
+                to: "q4",
+                label: "c",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs("q1", vec!["q4"], arcs);
+        assert!(!fsa.is_deterministic());
+
+        let (encoded, table) = fsa.encode();
+        let determinized = encoded.determinize();
+        assert!(determinized.is_deterministic());
+
+        let decoded = table.decode(&determinized).unwrap();
+        assert!(fsa.equivalent(&decoded));
+    }
+}
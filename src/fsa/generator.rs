@@ -1,67 +1,281 @@
+use std::cmp::{self, Ordering};
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
-use fsa::{Arc, Automaton};
+use std::ops::Add;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::{panic, thread, vec};
+use fsa::{Arc, Automaton, Label, Semiring};
 use log_domain::LogDomain;
-use num_traits::One;
 
 /// Language iterator for an `Automaton`.
 /// A `BatchGenerator` will extract the n best runs of an `Automaton`
 /// and yield a `WordGenerator` that iterates over these n words.
-pub struct BatchGenerator<A>
+pub struct BatchGenerator<A, W = LogDomain<f32>>
 where
     A: Eq + Hash,
 {
     // the current FSA
     // after n iterations it is replaced by an FSA not containing the
     // words of all previous iterations
-    fsa: Automaton<A>,
+    fsa: Automaton<A, W>,
     // step size n
     step: usize,
+    // expected number of words that are still pulled
+    capacity: usize,
 }
 
-impl<A> BatchGenerator<A>
+impl<A, W> BatchGenerator<A, W>
 where
     A: Eq + Hash,
 {
     /// Initialize a `Generator` object that iterates over all words an `Automaton` recognizes.
-    pub fn new(fsa: Automaton<A>, step: usize) -> Self {
-        BatchGenerator { fsa, step }
+    pub fn new(fsa: Automaton<A, W>, step: usize) -> Self {
+        BatchGenerator::with_capacity(fsa, step, 0)
+    }
+
+    /// Initialize a `Generator` object like `new` for roughly `expected` words that will be
+    /// pulled from it. The words of each batch are allocated for up to `expected` words at
+    /// once, as well as the set of yielded words in `dedup`.
+    /// It yields the same words as `new`.
+    pub fn with_capacity(fsa: Automaton<A, W>, step: usize, expected: usize) -> Self {
+        BatchGenerator {
+            fsa,
+            step,
+            capacity: expected,
+        }
+    }
+
+    /// Consume a `BatchGenerator` to construct an `Iterator` that yields each
+    /// word only once, i.e. with the weight of its best run.
+    /// The n best runs of an `Automaton` may spell the same word if it is not
+    /// deterministic, e.g. if two transitions with the same label leave a state.
+    pub fn dedup(self) -> UniqueWords<A, W> {
+        let seen = HashSet::with_capacity(self.capacity);
+        UniqueWords {
+            batches: self,
+            batch: Vec::new().into_iter(),
+            seen,
+        }
+    }
+
+    /// Consume a `BatchGenerator` to construct an `Iterator` that yields each
+    /// word only once with the sum of the weights of its runs.
+    /// This is an approximation: after a batch, the words of the batch are removed from
+    /// the remaining language, so only the runs among the n best runs of that batch
+    /// are summed up. The weights are exact if the batch contains all runs of its words,
+    /// e.g. if the `Automaton` is acyclic and the step size is its number of runs.
+    pub fn summed(self) -> SummedWords<A, W> {
+        SummedWords {
+            batches: self,
+            batch: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<A, W> BatchGenerator<A, W>
+where
+    A: Ord + Hash + Clone,
+    W: Semiring,
+{
+    /// Consume a `BatchGenerator` to collect its `k` best words.
+    /// They are sorted from the best to the worst weight, words with the same
+    /// weight are sorted by their labels.
+    pub fn take_sorted(self, k: usize) -> Vec<(Vec<A>, W)> {
+        let mut words: Vec<(Vec<A>, W)> = self.flatten().take(k).collect();
+        // sort_by is stable, i.e. words with equal weights stay sorted by their labels
+        words.sort_by(|(word1, _), (word2, _)| word1.cmp(word2));
+        sort_by_weight(&mut words);
+        words
+    }
+}
+
+impl<A, W> BatchGenerator<A, W>
+where
+    A: Eq + Hash + Clone + Send + Sync + 'static,
+    W: Semiring + Send + 'static,
+{
+    /// Consume a `BatchGenerator` to construct an `Iterator` that computes each
+    /// batch on a worker thread while the previous batch is consumed.
+    /// It yields the same batches in the same order; the worker stays at most
+    /// one batch ahead and stops when the `Iterator` is dropped.
+    pub fn parallel(self) -> ParallelBatches<A, W> {
+        // a buffer of one batch lets the worker compute the next batch
+        // while the current one is consumed
+        let (sender, receiver) = sync_channel(1);
+        let worker = thread::spawn(move || {
+            for batch in self {
+                if sender.send(batch).is_err() {
+                    break;
+                }
+            }
+        });
+
+        ParallelBatches {
+            batches: receiver,
+            worker: Some(worker),
+        }
+    }
+}
+
+/// Iterates over the batches of a `BatchGenerator` that are computed on a
+/// worker thread.
+pub struct ParallelBatches<A, W = LogDomain<f32>> {
+    batches: Receiver<WordGenerator<A, W>>,
+    // joined after the last batch to pass on a panic of the worker
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl<A, W> Iterator for ParallelBatches<A, W> {
+    type Item = WordGenerator<A, W>;
+
+    fn next(&mut self) -> Option<WordGenerator<A, W>> {
+        match self.batches.recv() {
+            Ok(batch) => Some(batch),
+            Err(_) => {
+                if let Some(Err(cause)) = self.worker.take().map(thread::JoinHandle::join) {
+                    panic::resume_unwind(cause);
+                }
+                None
+            }
+        }
+    }
+}
+
+// sorts the words of a batch from the best to the worst weight
+fn sort_by_weight<A, W: Semiring>(batch: &mut [(Vec<A>, W)]) {
+    // OpenFst's representation orders weights from the best to the worst
+    batch.sort_by(|(_, w1), (_, w2)| {
+        w1.to_openfst()
+            .partial_cmp(&w2.to_openfst())
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Iterates over the words generated by a `BatchGenerator` and skips each
+/// word that was yielded before.
+pub struct UniqueWords<A, W = LogDomain<f32>>
+where
+    A: Eq + Hash,
+{
+    batches: BatchGenerator<A, W>,
+    // the current batch, sorted from the best to the worst word
+    batch: vec::IntoIter<(Vec<A>, W)>,
+    // words that were yielded before
+    seen: HashSet<Vec<A>>,
+}
+
+impl<A, W> Iterator for UniqueWords<A, W>
+where
+    A: Eq + Hash + Clone,
+    W: Semiring,
+{
+    type Item = (Vec<A>, W);
+
+    fn next(&mut self) -> Option<(Vec<A>, W)> {
+        loop {
+            if let Some((word, weight)) = self.batch.next() {
+                if self.seen.insert(word.clone()) {
+                    return Some((word, weight));
+                }
+            } else {
+                let mut batch: Vec<(Vec<A>, W)> = self.batches.next()?.collect();
+                sort_by_weight(&mut batch);
+                self.batch = batch.into_iter();
+            }
+        }
+    }
+}
+
+/// Iterates over the words generated by a `BatchGenerator` and sums up the
+/// weights of all runs of each word within a batch.
+pub struct SummedWords<A, W = LogDomain<f32>>
+where
+    A: Eq + Hash,
+{
+    batches: BatchGenerator<A, W>,
+    // the distinct words of the current batch, sorted from the best to the worst
+    batch: vec::IntoIter<(Vec<A>, W)>,
+}
+
+impl<A, W> Iterator for SummedWords<A, W>
+where
+    A: Eq + Hash + Clone,
+    W: Semiring + Add<Output = W>,
+{
+    type Item = (Vec<A>, W);
+
+    fn next(&mut self) -> Option<(Vec<A>, W)> {
+        loop {
+            if let Some(word) = self.batch.next() {
+                return Some(word);
+            }
+
+            let mut batch: Vec<(Vec<A>, W)> = Vec::new();
+            let mut positions: HashMap<Vec<A>, usize> = HashMap::new();
+            for (word, weight) in self.batches.next()? {
+                if let Some(&position) = positions.get(&word) {
+                    batch[position].1 = batch[position].1 + weight;
+                } else {
+                    positions.insert(word.clone(), batch.len());
+                    batch.push((word, weight));
+                }
+            }
+            sort_by_weight(&mut batch);
+            self.batch = batch.into_iter();
+        }
     }
 }
 
 /// Iterates over a batch of words generated by an `Automaton`.
-pub struct WordGenerator<T> {
-    epsilon: bool,
-    ends: Vec<usize>,
-    start_transitions: Vec<Arc<usize, T>>,
-    transition_from: Vec<Option<Arc<usize, T>>>,
+pub struct WordGenerator<T, W = LogDomain<f32>> {
+    epsilon: Option<W>,
+    ends: Vec<(usize, W)>,
+    start_transitions: Vec<Arc<usize, Label<T>, W>>,
+    transition_from: Vec<Option<Arc<usize, Label<T>, W>>>,
+}
+
+impl<T, W: Semiring> WordGenerator<T, W> {
+    // weight of a final state
+    fn final_weight(&self, state: usize) -> Option<W> {
+        self.ends
+            .iter()
+            .find(|&&(end, _)| end == state)
+            .map(|&(_, weight)| weight)
+    }
 }
 
-impl<T: Clone> Iterator for WordGenerator<T> {
-    type Item = (Vec<T>, LogDomain<f32>);
+impl<T: Clone, W: Semiring> Iterator for WordGenerator<T, W> {
+    type Item = (Vec<T>, W);
 
-    fn next(&mut self) -> Option<(Vec<T>, LogDomain<f32>)> {
-        if self.epsilon {
-            self.epsilon = false;
-            Some((Vec::new(), LogDomain::one()))
+    fn next(&mut self) -> Option<(Vec<T>, W)> {
+        if let Some(weight) = self.epsilon.take() {
+            Some((Vec::new(), weight))
         } else if self.start_transitions.is_empty() {
             None
         } else {
             let start_transition = self.start_transitions.remove(0);
             let mut weight = start_transition.weight;
-            let mut word: Vec<T> = vec![start_transition.label];
+            let mut word: Vec<T> = Vec::new();
+            if let Label::Symbol(symbol) = start_transition.label {
+                word.push(symbol);
+            }
             let mut current_end = start_transition.to;
+            let mut final_weight = self.final_weight(current_end);
 
-            while !self.ends.contains(&current_end) {
+            while final_weight.is_none() {
                 if let Some(ref current_transition) = self.transition_from[current_end] {
                     current_end = current_transition.to;
-                    word.push(current_transition.label.clone());
+                    if let Label::Symbol(ref symbol) = current_transition.label {
+                        word.push(symbol.clone());
+                    }
                     weight = weight * current_transition.weight;
                 } else {
                     panic!("Openfsa (Arc::language): arcs are inconsistent.");
                 }
+                final_weight = self.final_weight(current_end);
             }
 
-            Some((word, weight))
+            Some((word, weight * final_weight.unwrap()))
         }
     }
 }
@@ -69,13 +283,35 @@ impl<T: Clone> Iterator for WordGenerator<T> {
 // assume a set of arcs from n-best search, i.e.
 // * there are no loops
 // * only the first state has multiple outgoing transitions
-// computes the set of words
-fn language<T>(arcs: Vec<Arc<usize, T>>, start: usize, ends: Vec<usize>) -> WordGenerator<T>
+// computes the set of words, whose weights include the weights of the final states
+// epsilon transitions do not contribute to the words
+pub(crate) fn language<L, T, W>(
+    arcs: Vec<Arc<usize, L, W>>,
+    start: usize,
+    ends: Vec<(usize, W)>,
+) -> WordGenerator<T, W>
 where
+    L: Into<Label<T>>,
     T: Clone,
+    W: Semiring,
+{
+    language_with_capacity(arcs, start, ends, 0)
+}
+
+// like `language`, where the words are allocated for up to `words` words at once
+fn language_with_capacity<L, T, W>(
+    arcs: Vec<Arc<usize, L, W>>,
+    start: usize,
+    ends: Vec<(usize, W)>,
+    words: usize,
+) -> WordGenerator<T, W>
+where
+    L: Into<Label<T>>,
+    T: Clone,
+    W: Semiring,
 {
     let mut arc_from = Vec::new();
-    let mut starts = Vec::new();
+    let mut starts = Vec::with_capacity(words);
     for arc in arcs {
         let Arc {
             from,
@@ -87,7 +323,7 @@ where
             starts.push(Arc {
                 from,
                 to,
-                label: label.clone(),
+                label: label.into(),
                 weight,
             });
         } else {
@@ -98,34 +334,42 @@ where
             arc_from[from] = Some(Arc {
                 from,
                 to,
-                label: label.clone(),
+                label: label.into(),
                 weight,
             });
         }
     }
 
     WordGenerator {
-        epsilon: ends.contains(&start),
+        epsilon: ends.iter().find(|&&(end, _)| end == start).map(|&(_, weight)| weight),
         ends,
         start_transitions: starts,
         transition_from: arc_from,
     }
 }
 
-impl<A> Iterator for BatchGenerator<A>
+impl<A, W> Iterator for BatchGenerator<A, W>
 where
     A: Eq + Hash + Clone,
+    W: Semiring,
 {
-    type Item = WordGenerator<A>;
+    type Item = WordGenerator<A, W>;
 
-    fn next(&mut self) -> Option<WordGenerator<A>> {
-        let nbest = self.fsa.n_best_automaton(self.step);
-        self.fsa = self.fsa.difference(&nbest);
+    fn next(&mut self) -> Option<WordGenerator<A, W>> {
+        let nbest = self.fsa.n_best_automaton(self.step, false);
+        // stop if the remaining language cannot be computed
+        self.fsa = self.fsa.difference(&nbest).ok()?;
 
         {
-            let (arcs, start, ends) = nbest.into_arcs();
-            if !ends.is_empty() && arcs.iter().any(|arc| arc.from == start) {
-                Some(language(arcs, start, ends))
+            let ends = nbest.final_weights();
+            let (arcs, start, _) = nbest.into_arcs();
+            // no transition leaves the initial state if the batch only contains the empty word
+            let epsilon = ends.iter().any(|&(end, _)| end == start);
+            if epsilon || arcs.iter().any(|arc| arc.from == start) {
+                // a batch contains at most n of the expected words
+                let words = cmp::min(self.step, self.capacity);
+                self.capacity -= words;
+                Some(language_with_capacity(arcs, start, ends, words))
             } else {
                 None
             }
@@ -136,9 +380,10 @@ where
 #[cfg(test)]
 mod test {
     use fsa::{Arc, Automaton};
+    use fsa::tests::loop_automaton;
     use log_domain::LogDomain;
     use num_traits::One;
-    use super::language;
+    use super::{language, BatchGenerator};
 
     #[test]
     fn simple_language() {
@@ -158,40 +403,149 @@ mod test {
         ];
         let words: Vec<(Vec<&str>, LogDomain<f32>)> =
             vec![(vec!["a", "word"], LogDomain::new(0.9).unwrap())];
-        let words_: Vec<(Vec<&str>, LogDomain<f32>)> = language(arcs, 1, vec![3]).collect();
+        let words_: Vec<(Vec<&str>, LogDomain<f32>)> =
+            language(arcs, 1, vec![(3, LogDomain::one())]).collect();
 
         assert_eq!(words, words_);
     }
 
     #[test]
     fn n_best() {
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (Vec::new(), LogDomain::one()),
+            (vec!["a", "word"], LogDomain::new(0.9).unwrap()),
+        ];
+
+        let nbest = loop_automaton().n_best_automaton(2, false);
+        let qfs = nbest.final_weights();
+        match nbest.into_arcs() {
+            (arcs, q0, _) => {
+                let words_: Vec<(Vec<&str>, LogDomain<f32>)> = language(arcs, q0, qfs).collect();
+                assert_eq!(words, words_)
+            }
+        };
+    }
+
+    #[test]
+    fn unique_words() {
         let arcs: Vec<Arc<&str, &str>> = vec![
             Arc {
                 from: "1",
                 to: "2",
                 label: "a",
-                weight: LogDomain::new(0.9).unwrap(),
+                weight: LogDomain::new(0.3).unwrap(),
             },
             Arc {
-                from: "2",
-                to: "1",
-                label: "word",
-                weight: LogDomain::one(),
+                from: "1",
+                to: "3",
+                label: "a",
+                weight: LogDomain::new(0.2).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("1", vec!["2", "3"], arcs);
+
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> =
+            BatchGenerator::new(fsa.clone(), 2).flatten().collect();
+        assert_eq!(2, words.len());
+
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> =
+            BatchGenerator::new(fsa, 2).dedup().collect();
+        assert_eq!(vec![(vec!["a"], LogDomain::new(0.3).unwrap())], words);
+    }
+
+    #[test]
+    fn summed_words() {
+        let arcs: Vec<Arc<&str, &str>> = vec![
+            Arc {
+                from: "1",
+                to: "2",
+                label: "a",
+                weight: LogDomain::new(0.3).unwrap(),
+            },
+            Arc {
+                from: "1",
+                to: "3",
+                label: "a",
+                weight: LogDomain::new(0.2).unwrap(),
             },
         ];
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> =
+            Automaton::from_arcs("1", vec!["2", "3"], arcs).generate_summed(2).collect();
+
+        assert_eq!(1, words.len());
+        assert_eq!(vec!["a"], words[0].0);
+        assert!((words[0].1.value() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn parallel_batches() {
+        let fsa = loop_automaton();
+
+        let sequential: Vec<(Vec<&str>, LogDomain<f32>)> =
+            fsa.clone().generate(2).flatten().take(7).collect();
+        let parallel: Vec<(Vec<&str>, LogDomain<f32>)> =
+            fsa.generate_parallel(2).flatten().take(7).collect();
+
+        assert_eq!(7, parallel.len());
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn sorted_words() {
+        let ww = LogDomain::new(0.9).unwrap();
         let words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
             (Vec::new(), LogDomain::one()),
-            (vec!["a", "word"], LogDomain::new(0.9).unwrap()),
+            (vec!["a", "word"], ww),
+            (vec!["a", "word", "a", "word"], ww.pow(2.0)),
+            (vec!["a", "word", "a", "word", "a", "word"], ww.pow(3.0)),
         ];
 
-        match Automaton::from_arcs("1", vec!["1"], arcs.clone())
-            .n_best_automaton(2)
-            .into_arcs()
-        {
-            (arcs, q0, qf) => {
-                let words_: Vec<(Vec<&str>, LogDomain<f32>)> = language(arcs, q0, qf).collect();
-                assert_eq!(words, words_)
-            }
-        };
+        assert_eq!(words, loop_automaton().generate(2).take_sorted(4));
+    }
+
+    #[test]
+    fn sorted_ties() {
+        let arcs: Vec<Arc<&str, &str>> = vec!["c", "a", "b"]
+            .into_iter()
+            .map(|label| Arc {
+                from: "1",
+                to: "2",
+                label,
+                weight: LogDomain::new(0.5).unwrap(),
+            })
+            .collect();
+        let fsa = Automaton::from_arcs("1", vec!["2"], arcs);
+
+        let words: Vec<Vec<&str>> = BatchGenerator::new(fsa, 2)
+            .take_sorted(3)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect();
+        assert_eq!(vec![vec!["a"], vec!["b"], vec!["c"]], words);
+    }
+
+    #[test]
+    fn capacity_hint() {
+        let fsa = loop_automaton();
+
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> =
+            fsa.clone().generate(2).flatten().take(5).collect();
+        let unique: Vec<(Vec<&str>, LogDomain<f32>)> =
+            fsa.clone().generate(2).dedup().take(5).collect();
+        for &expected in &[0, 1, 5, 100] {
+            let words_: Vec<(Vec<&str>, LogDomain<f32>)> = fsa.clone()
+                .generate_with_capacity(2, expected)
+                .flatten()
+                .take(5)
+                .collect();
+            assert_eq!(words, words_);
+
+            let unique_: Vec<(Vec<&str>, LogDomain<f32>)> = fsa.clone()
+                .generate_with_capacity(2, expected)
+                .dedup()
+                .take(5)
+                .collect();
+            assert_eq!(unique, unique_);
+        }
     }
 }
@@ -1,428 +1,4578 @@
+pub mod builder;
+pub mod encode;
 pub mod generator;
+pub mod semiring;
 
-use std::rc::Rc;
+#[cfg(feature = "flate2")]
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 use std::fmt::{Debug, Display, Error, Formatter};
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
 use openfsa_sys::*;
 use integeriser::{HashIntegeriser, Integeriser};
 use libc::{c_float, c_int};
 use log_domain::LogDomain;
+use num_traits::{One, Zero};
+use rand::Rng;
 use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::error;
 use std::io;
+use std::io::BufRead;
+use std::marker::PhantomData;
+use std::sync;
 
-use fsa::generator::BatchGenerator;
+use fsa::encode::EncodeTable;
+use fsa::generator::{language, BatchGenerator, ParallelBatches, SummedWords};
+pub use fsa::semiring::{Semiring, Tropical};
 
+/// Number of words that `Automaton::words` generates at once.
+pub const WORDS_STEP: usize = 16;
 
-/// Transition of an FSA with states of type `Q` and labels of type `A`.
+/// Version of the format of serialized Automata.
+/// Deserialization fails for Automata that were serialized in another version.
+pub const SERIALIZATION_VERSION: u32 = 1;
+
+/// Maximum number of steps `Automaton::total_weight` takes to verify that the
+/// sum of the weights of infinitely many runs converges.
+pub const CONVERGENCE_STEPS: usize = 1000;
+
+
+/// Transition of an FSA with states of type `Q`, labels of type `A`
+/// and weights of type `W`.
+/// The derived ordering compares the fields in the order of their declaration,
+/// i.e. the weight only decides between transitions with the same states and label;
+/// see `Arc::cmp_by_weight` to order transitions by their weights.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Arc<Q, T> {
+pub struct Arc<Q, T, W = LogDomain<f32>> {
     pub from: Q,
     pub to: Q,
     pub label: T,
-    pub weight: LogDomain<f32>,
+    pub weight: W,
 }
 
-///  Data type for finite state automata with labels of type `A`.
+impl<Q, T, W: Semiring> Arc<Q, T, W> {
+    /// Compares two transitions by their weights only, such that the transition
+    /// with the better weight comes first, e.g. the greater probability.
+    /// Incomparable weights, e.g. NaN, are considered equal.
+    pub fn cmp_by_weight(&self, other: &Self) -> Ordering {
+        // OpenFst's representation orders weights from the best to the worst
+        self.weight
+            .to_openfst()
+            .partial_cmp(&other.weight.to_openfst())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// A word whose labels of type `A` are integerized by `Automaton::compile_query`.
+/// It keeps the labels of the `Automaton` that compiled it, so it is only
+/// queried in Automata that share these labels.
 #[derive(Clone)]
-pub struct Automaton<A: Hash + Eq> {
-    fsa: Rc<fsa_t>,
-    labels: Rc<HashIntegeriser<A>>,
+pub struct CompiledWord<A: Hash + Eq> {
+    labels: Vec<c_int>,
+    symbols: sync::Arc<HashIntegeriser<A>>,
 }
 
-impl<T> Automaton<T>
-where
-    T: Hash + Eq,
-{
-    /// Hadarmard product of two Automata.
-    /// Consumes both Automata and returns an `Automaton` whose language contains
-    /// the intersection of both Automata's languages.
-    pub fn intersect(&self, other: &Automaton<T>) -> Self {
-        Automaton {
-            fsa: Rc::new(unsafe {
-                fsa_intersect(self.fsa.borrow(), other.fsa.borrow())
-            }),
-            labels: Rc::clone(&self.labels),
-        }
+impl<A: Hash + Eq> PartialEq for CompiledWord<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.labels == other.labels && sync::Arc::ptr_eq(&self.symbols, &other.symbols)
     }
+}
 
-    /// Hadamard product with inverted automaton.
-    /// Consumes both Automata and returns an `Automaton` whose
-    /// language contains the words contained in the language
-    /// of the first `Automaton` minus the words contained in the language of the second one.
-    pub fn difference(&self, other: &Automaton<T>) -> Self {
-        Automaton {
-            fsa: Rc::new(unsafe {
-                fsa_difference(self.fsa.borrow(), other.fsa.borrow())
-            }),
-            labels: Rc::clone(&self.labels),
-        }
+impl<A: Hash + Eq> Eq for CompiledWord<A> {}
+
+impl<A: Hash + Eq> Debug for CompiledWord<A> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "CompiledWord({:?})", self.labels)
     }
+}
 
-    // automaton containing the n best words
-    fn n_best_automaton(&self, n: usize) -> Self {
-        let nbest = unsafe { fsa_n_best(self.fsa.borrow(), n as c_int) };
+/// Errors that occur during operations on an `Automaton`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FsaError {
+    /// The named operation failed in OpenFst and did not produce an FSA.
+    OperationFailed(&'static str),
+    /// A transition's label id, as stored in OpenFst, has no label.
+    /// Epsilon transitions have the label id 0.
+    UnknownLabel(usize),
+    /// The weight of the transition between two states is not a valid weight,
+    /// e.g. NaN.
+    InvalidWeight(usize, usize),
+    /// The weight of the transition at this position of a list of transitions
+    /// is zero or not finite.
+    InvalidArc(usize),
+    /// The sum of the weights of infinitely many runs does not converge.
+    Divergent,
+    /// The language of an `Automaton` is empty.
+    EmptyLanguage,
+    /// An integerized state or label exceeds the integers of OpenFst.
+    IdOverflow(usize),
+    /// Two Automata do not have the same states and transitions.
+    DifferentStructure,
+    /// The final state is not reachable from the initial state.
+    UnreachableFinal(usize),
+}
 
-        Automaton {
-            fsa: Rc::new(nbest),
-            labels: Rc::clone(&self.labels),
+impl Display for FsaError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            FsaError::OperationFailed(operation) => {
+                write!(f, "OpenFst failed to compute the {}", operation)
+            }
+            FsaError::UnknownLabel(label) => write!(f, "there is no label with id {}", label),
+            FsaError::InvalidWeight(from, to) => write!(
+                f,
+                "the transition from state {} to state {} has an invalid weight",
+                from, to
+            ),
+            FsaError::InvalidArc(position) => write!(
+                f,
+                "the transition at position {} has a weight that is zero or not finite",
+                position
+            ),
+            FsaError::Divergent => write!(f, "the sum of the weights of the runs diverges"),
+            FsaError::EmptyLanguage => write!(f, "the language is empty"),
+            FsaError::IdOverflow(id) => write!(f, "the id {} exceeds the range of c_int", id),
+            FsaError::DifferentStructure => write!(f, "the automata differ in their structure"),
+            FsaError::UnreachableFinal(state) => {
+                write!(f, "the final state {} is not reachable", state)
+            }
         }
     }
+}
 
-    /// Read an `Automaton` from a binary file.
-    pub fn read_binary<R>(labels: Rc<HashIntegeriser<T>>, reader: R) -> io::Result<Automaton<T>>
-    where
-        R: io::Read,
-    {
-        let mut rvec: Vec<u8> = {
-            let res: io::Result<Vec<u8>> = reader.bytes().collect();
-            res?
-        };
-        let cvec = vec_t::new(&mut rvec);
-        Ok(Automaton {
-            labels,
-            fsa: Rc::new(unsafe { fsa_from_string(&cvec) }),
-        })
-    }
+impl error::Error for FsaError {}
 
-    /// Dump an `Automaton` to a binary file.
-    pub fn write_binary<F>(&self, writer: &mut F) -> io::Result<()>
-    where
-        F: io::Write,
-    {
-        let cvec = unsafe { fsa_to_string(self.fsa.borrow()) };
-        let slice: &[u8] = cvec.as_slice();
-        writer.write_all(slice)
+// converts an integerized state or label to its integer in OpenFst
+// fails instead of wrapping around if it exceeds the range of c_int
+pub(crate) fn checked_id(id: usize) -> Result<c_int, FsaError> {
+    if id > c_int::MAX as usize {
+        Err(FsaError::IdOverflow(id))
+    } else {
+        Ok(id as c_int)
     }
+}
 
-    /// Consume an `Automaton` to construct an `Iterator` that iterates over
-    /// all words contained in its language.
-    /// Internally, it will repeatedly generate the `step` best words contained in the
-    /// language of this `Automaton` and gradually yield those words.
-    pub fn generate(self, step: usize) -> BatchGenerator<T> {
-        BatchGenerator::new(self, step)
+/// Label of a transition that is either epsilon or a symbol of type `A`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Label<A> {
+    Epsilon,
+    Symbol(A),
+}
+
+impl<A> From<A> for Label<A> {
+    fn from(symbol: A) -> Self {
+        Label::Symbol(symbol)
     }
 }
 
-impl<T> Automaton<T>
-where
-    T: Hash + Eq + Display + Clone,
-{
-    /// Dump the symbol table to tab seperated values.
-    pub fn write_symbols<F>(&self, writer: &mut F) -> io::Result<()>
-    where
-        F: io::Write,
-    {
-        let labels = Borrow::<HashIntegeriser<T>>::borrow(&self.labels);
-        for label_id in 0..(labels.size()) {
-            if let Err(e) = write!(
-                writer,
-                "{}\t{}\n",
-                labels.find_value(label_id).unwrap(),
-                label_id + 1
-            ) {
-                return Err(e);
-            }
+impl<A: Display> Display for Label<A> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Label::Epsilon => write!(f, "ε"),
+            Label::Symbol(ref symbol) => write!(f, "{}", symbol),
         }
-        Ok(())
     }
 }
 
-impl<A> Automaton<A>
+/// Direction in which `Automaton::push_weights` moves the weights.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PushDirection {
+    /// Weights are moved towards the initial state.
+    ToInitial,
+    /// Weights are moved towards the final states.
+    ToFinal,
+}
+
+/// Order of the transitions leaving each state after `Automaton::arc_sort`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    /// Transitions are sorted by their labels, as expected by `intersect`.
+    ILabel,
+    /// Transitions are sorted by their target states.
+    State,
+}
+
+/// Kind of the Kleene closure of an `Automaton`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClosureKind {
+    /// Arbitrary repetitions of words, including the empty word with weight one.
+    Star,
+    /// At least one repetition of words; the empty word is only accepted
+    /// if it is already contained in the language.
+    Plus,
+}
+
+/// Properties of an `Automaton` that are computed by OpenFst, see `Automaton::properties`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Properties {
+    /// Each state has at most one outgoing transition per label,
+    /// where epsilon is a label as well.
+    pub deterministic: bool,
+    /// There are epsilon transitions.
+    pub epsilons: bool,
+    /// The transitions leaving each state are sorted by their labels.
+    pub label_sorted: bool,
+    /// Some transition or final state has a weight other than one and zero.
+    pub weighted: bool,
+    /// There are cycles.
+    pub cyclic: bool,
+    /// Each transition leads from a state to a greater one.
+    pub top_sorted: bool,
+    /// Each state is reachable from the initial state.
+    pub accessible: bool,
+    /// A final state is reachable from each state.
+    pub coaccessible: bool,
+}
+
+///  Data type for finite state automata with labels of type `A`.
+/// The `Semiring` `W` determines the type of the weights, the default are probabilities.
+/// An `Automaton` is immutable, its clones share the same FSA and labels;
+/// it can be sent to and shared among threads if `A` can.
+#[derive(Clone)]
+pub struct Automaton<A: Hash + Eq, W = LogDomain<f32>> {
+    fsa: sync::Arc<fsa_t>,
+    labels: sync::Arc<HashIntegeriser<A>>,
+    weights: PhantomData<W>,
+}
+
+impl<T, W> Automaton<T, W>
 where
-    A: Hash + Eq + Clone,
+    T: Hash + Eq,
+    W: Semiring,
 {
-    // constructs a FSA with integerized transition labels
-    // uses an existing integerizer to unify labels
-    fn from_arcs_with_labels<Q>(
-        initial_state: Q,
-        final_states: Vec<Q>,
-        arcs: Vec<Arc<Q, A>>,
-        i_labels: &mut HashIntegeriser<A>,
-    ) -> fsa_t
-    where
-        Q: Hash + Eq + Clone,
-    {
-        let mut i_states = HashIntegeriser::new();
+    // wraps an fsa whose labels are integerized by `labels`,
+    // e.g. the projection of a `Transducer`
+    pub(crate) fn from_fsa(fsa: fsa_t, labels: sync::Arc<HashIntegeriser<T>>) -> Self {
+        Automaton {
+            fsa: sync::Arc::new(fsa),
+            labels,
+            weights: PhantomData,
+        }
+    }
 
-        // ensure initial state = 0, final state in i_states
-        i_states.integerise(initial_state);
-        let mut qfs = Vec::new();
-        for final_state in final_states {
-            qfs.push(i_states.integerise(final_state) as c_int);
+    // wraps the result of an operation in OpenFst
+    // fails if OpenFst did not produce an fsa
+    fn checked(
+        fsa: fsa_t,
+        labels: sync::Arc<HashIntegeriser<T>>,
+        operation: &'static str,
+    ) -> Result<Self, FsaError> {
+        if fsa.is_null() {
+            Err(FsaError::OperationFailed(operation))
+        } else {
+            Ok(Automaton {
+                fsa: sync::Arc::new(fsa),
+                labels,
+                weights: PhantomData,
+            })
         }
+    }
 
-        let mut carcs: Vec<fsa_arc> = Vec::new();
-        for arc in arcs {
-            let Arc {
-                from,
-                to,
-                label,
-                weight,
-            } = arc;
-            carcs.push(fsa_arc {
-                from_state: i_states.integerise(from) as c_int,
-                to_state: i_states.integerise(to) as c_int,
-                label: (i_labels.integerise(label) + 1) as c_int,
-                weight: -weight.ln() as c_float,
-            });
+    /// Kleene closure of an `Automaton`.
+    /// Returns an `Automaton` whose language contains all concatenations of words
+    /// contained in the language of this `Automaton`.
+    /// Whether the empty word is accepted is determined by the `ClosureKind`.
+    pub fn closure(&self, kind: ClosureKind) -> Self {
+        let plus = match kind {
+            ClosureKind::Star => 0,
+            ClosureKind::Plus => 1,
+        };
+
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_closure(self.fsa.borrow(), plus) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
         }
+    }
 
-        unsafe {
-            fsa_from_arc_list(
-                i_states.size() as c_int,
-                &vec_t::new(&mut qfs),
-                &vec_t::new(&mut carcs),
-            )
+    /// Reversal of an `Automaton`.
+    /// Returns an `Automaton` whose language contains the reversed words
+    /// of this `Automaton`'s language with the same weights.
+    /// The reversal introduces a new initial state, so the states listed by
+    /// `into_arcs` are numbered differently than the ones of this `Automaton`.
+    pub fn reverse(&self) -> Self {
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_reverse(self.fsa.borrow()) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
         }
     }
 
-    /// Default constructor for an `Automaton`.
-    /// Consumes a list of `Arc` transitions and stores all labels
-    /// of type `A` in an `Integerizer<A>`.
-    /// The original states of type `Q` are lost after integerization.
-    pub fn from_arcs<Q>(initial_state: Q, final_state: Vec<Q>, arcs: Vec<Arc<Q, A>>) -> Automaton<A>
-    where
-        Q: Hash + Eq + Clone,
-    {
-        // start with one per default, since zero is reserved for epsilon
-        let mut integeriser = HashIntegeriser::new();
-        let fsa = Rc::new(Automaton::from_arcs_with_labels(
-            initial_state,
-            final_state,
-            arcs,
-            &mut integeriser,
-        ));
+    /// Minimization of an `Automaton`.
+    /// Returns an `Automaton` that recognizes the same weighted language
+    /// where equivalent states are merged.
+    /// The `Automaton` is expected to be deterministic, so this should be chained
+    /// after `determinize`.
+    pub fn minimize(&self) -> Self {
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_minimize(self.fsa.borrow()) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        }
+    }
+
+    /// Sorts the transitions leaving each state of an `Automaton` by a `SortKey`.
+    /// Returns an `Automaton` that recognizes the same weighted language.
+    /// Since `intersect` expects transitions sorted by `SortKey::ILabel`,
+    /// an `Automaton` sorted by `SortKey::State` should be sorted again before.
+    pub fn arc_sort(&self, by: SortKey) -> Self {
+        let by_state = match by {
+            SortKey::ILabel => 0,
+            SortKey::State => 1,
+        };
 
         Automaton {
-            fsa,
-            labels: Rc::new(integeriser),
+            fsa: sync::Arc::new(unsafe { fsa_arc_sort(self.fsa.borrow(), by_state) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
         }
     }
 
-    /// Alternative constructor for an `Automaton`.
-    /// Synchronizes label integerization using the labels of an existing
-    /// `Automaton` and consumes a `Vec`tor of `Arc`s like `from_arcs`.
-    /// The `Integerizer` storing the labels of the first `Automaton`
-    /// is expanded to store the labels of both Automata.
-    pub fn from_arcs_with_same_labels<Q>(
-        &self,
-        initial_state: Q,
-        final_state: Vec<Q>,
-        arcs: Vec<Arc<Q, A>>,
-    ) -> Automaton<A>
+    /// Renumbers the states of an acyclic `Automaton` in topological order, i.e. each
+    /// transition leads from a state to a greater one.
+    /// Returns an `Automaton` that recognizes the same weighted language,
+    /// or `None` if the `Automaton` contains a cycle.
+    pub fn topsort(&self) -> Option<Self> {
+        let fsa = unsafe { fsa_topsort(self.fsa.borrow()) };
+        Automaton::checked(fsa, sync::Arc::clone(&self.labels), "topological sort").ok()
+    }
+
+    /// Trims an `Automaton`.
+    /// Returns an `Automaton` that recognizes the same weighted language
+    /// without the states that are not on any accepting path.
+    /// Since it removes dead ends, e.g. after `intersect`, it also speeds up `generate`.
+    pub fn connect(&self) -> Self {
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_connect(self.fsa.borrow()) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        }
+    }
+
+    /// Prunes an `Automaton`.
+    /// Returns an `Automaton` without the transitions and states that are only on
+    /// runs whose weights are worse than the weight of the best run times `threshold`,
+    /// e.g. with probabilities, a `threshold` of 0.1 discards every run that
+    /// is less than a tenth as likely as the best one.
+    pub fn prune(&self, threshold: W) -> Self {
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_prune(self.fsa.borrow(), threshold.to_openfst()) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        }
+    }
+
+    /// Applies a function to the weight of each transition and final state of an `Automaton`,
+    /// e.g. to raise the weights to a power.
+    /// Returns an `Automaton` with the same states, transitions and labels.
+    pub fn map_weights<F>(&self, f: F) -> Self
     where
-        Q: Hash + Eq + Clone,
+        F: Fn(W) -> W,
     {
-        let mut integeriser = (*self.labels).clone();
-        let fsa = Rc::new(Automaton::from_arcs_with_labels(
-            initial_state,
-            final_state,
-            arcs,
-            &mut integeriser,
-        ));
+        let carcs = unsafe { fsa_to_arc_list(self.fsa.borrow()) };
+        let mut arc_weights: Vec<c_float> = carcs
+            .as_slice::<fsa_arc>()
+            .iter()
+            .map(|carc| f(W::from_openfst(carc.weight)).to_openfst())
+            .collect();
+        let qfs = unsafe { fsa_final_weights(self.fsa.borrow()) };
+        let mut final_weights: Vec<fsa_final> = qfs
+            .as_slice::<fsa_final>()
+            .iter()
+            .map(|qf| fsa_final {
+                state: qf.state,
+                weight: f(W::from_openfst(qf.weight)).to_openfst(),
+            })
+            .collect();
 
+        let fsa = unsafe {
+            fsa_reweight(
+                self.fsa.borrow(),
+                &*vec_t::new(&mut arc_weights),
+                &*vec_t::new(&mut final_weights),
+            )
+        };
         Automaton {
-            fsa,
-            labels: Rc::new(integeriser),
+            fsa: sync::Arc::new(fsa),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
         }
     }
 
-    // todo: return arc iterator
-    /// Lists the `Arc`s of an `Automaton`.
-    /// Since the original type of states cannot be recovered, we use `usize`.
-    pub fn into_arcs(self) -> (Vec<Arc<usize, A>>, usize, Vec<usize>) {
-        let (carcs, q0, qfs): (Vec<fsa_arc>, c_int, Vec<c_int>) = unsafe {
-            let carcs = fsa_to_arc_list(self.fsa.borrow());
-            let qi = fsa_initial_state(self.fsa.borrow());
-            let qfs = fsa_final_states(self.fsa.borrow());
+    /// The labels of an `Automaton` with their integers; OpenFst stores the
+    /// label with integer `i` as `i + 1`, see `into_raw_arcs`.
+    /// Automata that share these integers, e.g. those constructed by
+    /// `from_arcs_with_same_labels`, are combined without relabeling.
+    pub fn symbols(&self) -> &HashIntegeriser<T> {
+        &self.labels
+    }
 
-            (carcs.to_vec(), qi, qfs.to_vec())
-        };
+    /// Number of states of an `Automaton`, including states without transitions.
+    pub fn num_states(&self) -> usize {
+        unsafe { fsa_num_states(self.fsa.borrow()) as usize }
+    }
+
+    /// Initial state of an `Automaton` as listed by `into_arcs`.
+    pub fn initial_state(&self) -> usize {
+        unsafe { fsa_initial_state(self.fsa.borrow()) as usize }
+    }
+
+    /// Final states of an `Automaton` as listed by `into_arcs`.
+    /// Unlike `into_arcs`, this does not decode the transitions.
+    pub fn final_states(&self) -> Vec<usize> {
+        let qfs = unsafe { fsa_final_states(self.fsa.borrow()) };
+        qfs.as_slice::<c_int>().iter().map(|&q| q as usize).collect()
+    }
+
+    /// Checks if a state of an `Automaton` is final without listing all final states.
+    /// A state that does not exist is not final.
+    pub fn is_final(&self, state: usize) -> bool {
+        // states beyond the range of c_int do not exist
+        checked_id(state)
+            .map(|state| unsafe { fsa_is_final(self.fsa.borrow(), state) != 0 })
+            .unwrap_or(false)
+    }
+
+    /// Final states of an `Automaton` with their weights.
+    /// Final states without an explicit weight are reported with weight one.
+    pub fn final_weights(&self) -> Vec<(usize, W)> {
+        let qfs = unsafe { fsa_final_weights(self.fsa.borrow()) };
+        qfs.as_slice::<fsa_final>()
+            .iter()
+            .map(|qf| (qf.state as usize, W::from_openfst(qf.weight)))
+            .collect()
+    }
 
+    /// Lists the transitions of an `Automaton` as they are stored in OpenFst, i.e. tuples
+    /// `(from, to, label, weight)` of the label's integer, where 0 is epsilon and
+    /// the other labels are shifted by one, and the weight's representation in OpenFst.
+    /// In contrast to `into_arcs`, neither labels nor weights are decoded.
+    #[allow(clippy::type_complexity)]
+    pub fn into_raw_arcs(self) -> (Vec<(usize, usize, usize, f32)>, usize, Vec<usize>) {
+        let carcs = unsafe { fsa_to_arc_list(self.fsa.borrow()) };
         let arcs = carcs
-            .into_iter()
-            .map(|carc| match carc {
-                fsa_arc {
-                    from_state,
-                    to_state,
-                    label,
-                    weight,
-                } => Arc {
-                    from: from_state as usize,
-                    to: to_state as usize,
-                    label: self.labels
-                        .find_value((label - 1) as usize)
-                        .unwrap()
-                        .clone(),
-                    weight: LogDomain::new((-weight).exp()).unwrap(),
-                },
+            .as_slice::<fsa_arc>()
+            .iter()
+            .map(|carc| {
+                (
+                    carc.from_state as usize,
+                    carc.to_state as usize,
+                    carc.label as usize,
+                    carc.weight,
+                )
             })
             .collect();
 
-        (
-            arcs,
-            q0 as usize,
-            qfs.into_iter().map(|x| x as usize).collect(),
-        )
+        (arcs, self.initial_state(), self.final_states())
     }
-}
 
+    /// Number of transitions of an `Automaton`.
+    /// Unlike `into_arcs`, this neither allocates the transitions nor looks up their labels.
+    pub fn num_arcs(&self) -> usize {
+        unsafe { fsa_num_arcs(self.fsa.borrow()) as usize }
+    }
 
-use serde::ser::{Serialize, Serializer};
-use serde::de::{Deserialize, Deserializer};
+    /// Checks if the language of an `Automaton` is empty, i.e. if there is no
+    /// accepting path from the initial state to a final state.
+    pub fn is_empty(&self) -> bool {
+        unsafe { fsa_is_empty(self.fsa.borrow()) != 0 }
+    }
 
-impl<T> Serialize for Automaton<T>
-where
-    T: Serialize + Hash + Eq,
-{
-    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let &Automaton {
-            ref fsa,
-            ref labels,
-        } = self;
+    /// Checks that each final state of an `Automaton` is reachable from its initial state.
+    /// Constructors like `from_arcs` keep final states that are not reachable,
+    /// they do not contribute to the language and are removed by `connect`.
+    /// Fails with the first final state that is not reachable.
+    pub fn validate(&self) -> Result<(), FsaError> {
+        let successors = self.successors();
+        let mut reachable = vec![false; successors.len()];
+        let mut agenda = Vec::new();
+        // an Automaton without states has no initial state
+        if let Some(visited) = reachable.get_mut(self.initial_state()) {
+            *visited = true;
+            agenda.push(self.initial_state());
+        }
+        while let Some(state) = agenda.pop() {
+            for &to in &successors[state] {
+                if !reachable[to] {
+                    reachable[to] = true;
+                    agenda.push(to);
+                }
+            }
+        }
 
-        (
-            Borrow::<fsa_t>::borrow(fsa),
-            Borrow::<HashIntegeriser<T>>::borrow(labels),
-        ).serialize(serializer)
+        match self.final_states().into_iter().find(|&qf| !reachable[qf]) {
+            Some(qf) => Err(FsaError::UnreachableFinal(qf)),
+            None => Ok(()),
+        }
     }
-}
 
-impl<'de, T> Deserialize<'de> for Automaton<T>
-where
-    T: Deserialize<'de> + Hash + Eq + Clone,
-{
-    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Automaton<T>, D::Error> {
-        type Tup<T> = (fsa_t, HashIntegeriser<T>);
-        let (fsa, labels) = Tup::deserialize(deserializer)?;
+    /// Checks if a state of an `Automaton` is on a cycle, i.e. if it is reachable
+    /// from itself by at least one transition.
+    /// Runs of a cyclic `Automaton` may use such a state arbitrarily often.
+    /// A state that does not exist is not on a cycle.
+    pub fn state_on_cycle(&self, state: usize) -> bool {
+        let successors = self.successors();
+        if state >= successors.len() {
+            return false;
+        }
 
-        Ok(Automaton {
-            fsa: Rc::new(fsa),
-            labels: Rc::new(labels),
-        })
+        // depth-first search for a path back to the state
+        let mut visited = vec![false; successors.len()];
+        let mut agenda = successors[state].clone();
+        while let Some(current) = agenda.pop() {
+            if current == state {
+                return true;
+            }
+            if !visited[current] {
+                visited[current] = true;
+                agenda.extend(&successors[current]);
+            }
+        }
+        false
     }
-}
 
-impl<T> Debug for Automaton<T>
-where
-    T: Debug + Hash + Eq,
-{
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        write!(
-            f,
-            "Automaton {{ fsa: {:?}, labels: {:?} }}",
-            self.fsa,
-            self.labels
-        )
+    // lists the targets of the transitions leaving each state
+    fn successors(&self) -> Vec<Vec<usize>> {
+        let mut successors = vec![Vec::new(); self.num_states()];
+        let carcs = unsafe { fsa_to_arc_list(self.fsa.borrow()) };
+        for carc in carcs.as_slice::<fsa_arc>() {
+            successors[carc.from_state as usize].push(carc.to_state as usize);
+        }
+        successors
     }
-}
 
-impl<T> Display for Automaton<T>
-where
-    T: Display + Hash + Eq + Clone,
-{
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        let (arcs, q0, qfs) = self.clone().into_arcs();
+    /// Computes the `Properties` of an `Automaton` at once.
+    pub fn properties(&self) -> Properties {
+        let mask = properties::I_DETERMINISTIC
+            | properties::NO_EPSILONS
+            | properties::I_LABEL_SORTED
+            | properties::WEIGHTED
+            | properties::CYCLIC
+            | properties::TOP_SORTED
+            | properties::ACCESSIBLE
+            | properties::CO_ACCESSIBLE;
+        let bits = unsafe { fsa_properties(self.fsa.borrow(), mask) };
 
-        let qfs_strings: Vec<String> = qfs.iter().map(|q| format!("{}", q)).collect();
-        let arc_strings: Vec<String> = arcs.iter().map(|arc| format!("{}", arc)).collect();
+        Properties {
+            deterministic: bits & properties::I_DETERMINISTIC != 0,
+            epsilons: bits & properties::NO_EPSILONS == 0,
+            label_sorted: bits & properties::I_LABEL_SORTED != 0,
+            weighted: bits & properties::WEIGHTED != 0,
+            cyclic: bits & properties::CYCLIC != 0,
+            top_sorted: bits & properties::TOP_SORTED != 0,
+            accessible: bits & properties::ACCESSIBLE != 0,
+            coaccessible: bits & properties::CO_ACCESSIBLE != 0,
+        }
+    }
 
-        write!(
-            f,
-            "initial {}\nfinal: {}\n{}",
-            q0,
-            qfs_strings.join(", "),
-            arc_strings.join("\n")
-        )
+    /// Checks if an `Automaton` has no cycles; a transition from a state to itself
+    /// is a cycle as well.
+    /// If an `Automaton` is acyclic, `generate` yields only finitely many words.
+    pub fn is_acyclic(&self) -> bool {
+        let acyclic = unsafe { fsa_properties(self.fsa.borrow(), properties::ACYCLIC) };
+        acyclic != 0
     }
-}
 
-impl<T, Q> Display for Arc<Q, T>
-where
-    T: Display,
-    Q: Display,
-{
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        write!(
-            f,
-            "{}[{}]\t→ {} # {}",
-            self.from,
-            self.label,
-            self.to,
-            self.weight
-        )
+    /// Checks if each state of an `Automaton` has at most one outgoing transition
+    /// per label and there are no epsilon transitions.
+    /// If this holds, there is no need to `determinize` the `Automaton`.
+    /// This only checks the structure of the transitions; an `Automaton` that is
+    /// not deterministic may still be equivalent to a deterministic one.
+    pub fn is_deterministic(&self) -> bool {
+        let mask = properties::I_DETERMINISTIC | properties::NO_EPSILONS;
+        unsafe { fsa_properties(self.fsa.borrow(), mask) == mask }
+    }
+
+    /// Counts the accepting runs of an `Automaton`.
+    /// Returns `None` if the `Automaton` contains a cycle, since there are infinitely many
+    /// runs in that case and `generate` does not terminate, or if there are too many runs
+    /// to count them in a `u64`.
+    /// Each word is counted once per run, so for a deterministic `Automaton` this
+    /// is the number of words in its language.
+    pub fn count_paths(&self) -> Option<u64> {
+        let paths = unsafe { fsa_count_paths(self.fsa.borrow()) };
+        if paths == COUNT_OVERFLOW {
+            None
+        } else {
+            Some(paths as u64)
+        }
+    }
+
+    // automaton containing the n best words
+    // or containing the best runs of the n best distinct words if `unique` is set
+    fn n_best_automaton(&self, n: usize, unique: bool) -> Self {
+        // there are never more runs than integers in OpenFst
+        let n = checked_id(n).unwrap_or(c_int::MAX);
+        let nbest = unsafe { fsa_n_best(self.fsa.borrow(), n, unique as c_int) };
+
+        Automaton {
+            fsa: sync::Arc::new(nbest),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        }
+    }
+
+    /// Read an `Automaton` from a binary file.
+    /// Fails with `io::ErrorKind::InvalidData` if the file does not contain
+    /// a valid binary `Automaton`.
+    pub fn read_binary<R>(
+        labels: sync::Arc<HashIntegeriser<T>>,
+        mut reader: R,
+    ) -> io::Result<Automaton<T, W>>
+    where
+        R: io::Read,
+    {
+        let mut bytes: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Automaton::from_bytes(labels, bytes)
+    }
+
+    /// Read an `Automaton` from a gzip-compressed binary file as written by `write_binary_gz`.
+    /// Fails if the file is not gzip-compressed, and with `io::ErrorKind::InvalidData`
+    /// if the decompressed file does not contain a valid binary `Automaton`.
+    #[cfg(feature = "flate2")]
+    pub fn read_binary_gz<R>(
+        labels: sync::Arc<HashIntegeriser<T>>,
+        reader: R,
+    ) -> io::Result<Automaton<T, W>>
+    where
+        R: io::Read,
+    {
+        Automaton::read_binary(labels, GzDecoder::new(reader))
+    }
+
+    /// Decodes an `Automaton` from a binary string, e.g. the contents of a binary file.
+    /// In contrast to `read_binary`, the bytes are passed to OpenFst as they are.
+    /// Fails with `io::ErrorKind::InvalidData` if the bytes do not contain
+    /// a valid binary `Automaton`.
+    pub fn from_bytes(
+        labels: sync::Arc<HashIntegeriser<T>>,
+        mut bytes: Vec<u8>,
+    ) -> io::Result<Automaton<T, W>> {
+        let fsa = unsafe { fsa_from_string(&*vec_t::new(&mut bytes)) };
+
+        if fsa.is_null() {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "malformed binary automaton",
+            ))
+        } else {
+            Ok(Automaton {
+                labels,
+                fsa: sync::Arc::new(fsa),
+                weights: PhantomData,
+            })
+        }
+    }
+
+    /// Dump an `Automaton` to a binary file.
+    pub fn write_binary<F>(&self, writer: &mut F) -> io::Result<()>
+    where
+        F: io::Write,
+    {
+        let cvec = unsafe { fsa_to_string(self.fsa.borrow()) };
+        let slice: &[u8] = cvec.as_slice();
+        writer.write_all(slice)
+    }
+
+    /// Dump an `Automaton` to a gzip-compressed binary file.
+    /// The decompressed contents are those written by `write_binary`.
+    #[cfg(feature = "flate2")]
+    pub fn write_binary_gz<F>(&self, writer: &mut F) -> io::Result<()>
+    where
+        F: io::Write,
+    {
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        self.write_binary(&mut encoder)?;
+        encoder.finish().map(|_| ())
+    }
+
+    /// Encodes an `Automaton` as a binary string like `write_binary`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let cvec = unsafe { fsa_to_string(self.fsa.borrow()) };
+        cvec.to_vec()
+    }
+
+    /// Dump an `Automaton` in the AT&T text format of OpenFst.
+    /// Writes one line `from to label weight` per transition and
+    /// one line `state weight` per final state, where labels are the integers
+    /// written by `write_symbols` and weights are negative logarithms of probabilities.
+    /// The transitions of the initial state are written first.
+    /// If there are none, only the initial state is written if it is final.
+    pub fn write_att<F>(&self, writer: &mut F) -> io::Result<()>
+    where
+        F: io::Write,
+    {
+        let (carcs, q0, finals): (Vec<fsa_arc>, c_int, Vec<fsa_final>) = unsafe {
+            let carcs = fsa_to_arc_list(self.fsa.borrow());
+            let q0 = fsa_initial_state(self.fsa.borrow());
+            let finals = fsa_final_weights(self.fsa.borrow());
+
+            (carcs.to_vec(), q0, finals.to_vec())
+        };
+
+        if carcs.iter().any(|carc| carc.from_state == q0) {
+            let (initial, other): (Vec<&fsa_arc>, Vec<&fsa_arc>) =
+                carcs.iter().partition(|carc| carc.from_state == q0);
+            for carc in initial.into_iter().chain(other) {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}",
+                    carc.from_state,
+                    carc.to_state,
+                    carc.label,
+                    carc.weight
+                )?;
+            }
+            for qf in &finals {
+                writeln!(writer, "{}\t{}", qf.state, qf.weight)?;
+            }
+        } else if let Some(qf) = finals.iter().find(|qf| qf.state == q0) {
+            writeln!(writer, "{}\t{}", qf.state, qf.weight)?;
+        }
+
+        Ok(())
+    }
+
+    /// Consume an `Automaton` to construct an `Iterator` that iterates over
+    /// all words contained in its language.
+    /// Internally, it will repeatedly generate the `step` best words contained in the
+    /// language of this `Automaton` and gradually yield those words.
+    pub fn generate(self, step: usize) -> BatchGenerator<T, W> {
+        BatchGenerator::new(self, step)
+    }
+
+    /// Like `generate`, but expects that roughly `expected` words will be pulled;
+    /// see `BatchGenerator::with_capacity`. It yields the same words as `generate`.
+    pub fn generate_with_capacity(self, step: usize, expected: usize) -> BatchGenerator<T, W> {
+        BatchGenerator::with_capacity(self, step, expected)
+    }
+
+    /// Like `generate`, but computes each batch on a worker thread while the
+    /// previous batch is consumed; see `BatchGenerator::parallel`.
+    pub fn generate_parallel(self, step: usize) -> ParallelBatches<T, W>
+    where
+        T: Clone + Send + Sync + 'static,
+        W: Send + 'static,
+    {
+        self.generate(step).parallel()
+    }
+
+    /// Like `generate`, but yields each word once with the sum of the weights of its
+    /// runs; see `BatchGenerator::summed` for the limits of this approximation.
+    pub fn generate_summed(self, step: usize) -> SummedWords<T, W> {
+        self.generate(step).summed()
+    }
+
+    /// Consume an `Automaton` to construct an `Iterator` that yields each word
+    /// contained in its language with its weight.
+    /// Like `generate`, it computes the best words in batches of `WORDS_STEP`.
+    pub fn words(self) -> impl Iterator<Item = (Vec<T>, W)>
+    where
+        T: Clone,
+    {
+        self.generate(WORDS_STEP).flatten()
+    }
+
+    /// Consume an `Automaton` to construct an `Iterator` that yields each word
+    /// of at most `max_len` symbols contained in its language with its weight.
+    /// In contrast to `words`, it terminates for a cyclic `Automaton`.
+    /// Fails with `FsaError::IdOverflow` if `max_len` or the number of labels
+    /// exceeds the integers in OpenFst.
+    pub fn words_up_to(
+        self,
+        max_len: usize,
+    ) -> Result<impl Iterator<Item = (Vec<T>, W)>, FsaError>
+    where
+        T: Clone,
+    {
+        // all states and labels are checked once, so they can be cast below
+        let states = checked_id(max_len.saturating_add(1))?;
+        checked_id(self.labels.size())?;
+
+        // accepts each word of at most max_len symbols with weight one
+        let one = W::one().to_openfst();
+        let mut arcs = Vec::new();
+        for state in 0..max_len {
+            for label in 1..self.labels.size() + 1 {
+                arcs.push(fsa_arc {
+                    from_state: state as c_int,
+                    to_state: (state + 1) as c_int,
+                    label: label as c_int,
+                    weight: one,
+                });
+            }
+        }
+        let mut final_states: Vec<fsa_final> = (0..states)
+            .map(|state| fsa_final {
+                state,
+                weight: one,
+            })
+            .collect();
+        let bounded = unsafe {
+            fsa_from_arc_list(
+                states,
+                &*vec_t::new(&mut final_states),
+                &*vec_t::new(&mut arcs),
+            )
+        };
+        let bounded = Automaton::from_fsa(bounded, sync::Arc::clone(&self.labels));
+
+        // the intersection is acyclic, so its language is finite;
+        // if it cannot be computed, there are no words
+        Ok(self.intersect(&bounded)
+            .into_iter()
+            .flat_map(|fsa| fsa.words()))
+    }
+}
+
+// operations that combine the weights of runs in the log semiring
+impl<T> Automaton<T>
+where
+    T: Hash + Eq,
+{
+    /// Determinization of an `Automaton`.
+    /// Returns a deterministic `Automaton` that recognizes the same weighted language;
+    /// runs with the same word are combined and their weights are summed up.
+    /// The determinization may not terminate if the weighted `Automaton` is not determinizable.
+    pub fn determinize(&self) -> Self {
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_determinize(self.fsa.borrow()) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        }
+    }
+
+    /// Weight pushing of an `Automaton`.
+    /// Returns an `Automaton` that recognizes the same weighted language whose weights
+    /// are redistributed such that as much weight as possible is moved
+    /// in the given `PushDirection`.
+    /// Pushing towards the initial state makes the weights of the transitions leaving
+    /// each state sum up to one, which is numerically more stable for `generate`.
+    pub fn push_weights(&self, direction: PushDirection) -> Self {
+        let to_final = match direction {
+            PushDirection::ToInitial => 0,
+            PushDirection::ToFinal => 1,
+        };
+
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_push(self.fsa.borrow(), to_final) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        }
+    }
+
+    /// Shortest distances of the states of an `Automaton`.
+    /// Returns a `Vec`tor indexed by the states listed by `into_arcs` that contains
+    /// the sum of the weights of all runs from the initial state to each state,
+    /// or from each state to the final states including the final weights if `reverse` is set.
+    /// States that are not reachable have weight zero.
+    pub fn shortest_distance(&self, reverse: bool) -> Vec<LogDomain<f32>> {
+        let distances = unsafe { fsa_shortest_distance(self.fsa.borrow(), reverse as c_int) };
+
+        distances
+            .as_slice::<c_float>()
+            .iter()
+            .map(|&distance| LogDomain::from_openfst(distance))
+            .collect()
+    }
+
+    /// Sum of the weights of all accepting runs of an `Automaton`, i.e. the
+    /// partition function of its weighted language.
+    /// For a cyclic `Automaton`, this is an infinite series that converges iff the
+    /// spectral radius of the matrix of transition weights is below one.
+    /// Fails if convergence cannot be verified within `CONVERGENCE_STEPS` steps.
+    pub fn total_weight(&self) -> Result<LogDomain<f32>, FsaError> {
+        // states that are not on any accepting run do not contribute to the sum
+        let trimmed = self.connect();
+        if !trimmed.converges() {
+            return Err(FsaError::Divergent);
+        }
+
+        let distances = trimmed.shortest_distance(true);
+        Ok(distances
+            .get(trimmed.initial_state())
+            .cloned()
+            .unwrap_or_else(LogDomain::zero))
+    }
+
+    /// Normalization of an `Automaton`.
+    /// Returns an `Automaton` whose weights are rescaled such that the `total_weight`
+    /// of its language is one, i.e. a probability distribution over the words.
+    /// Fails if the language is empty or its total weight diverges.
+    pub fn normalize(&self) -> Result<Self, FsaError> {
+        if self.total_weight()?.is_zero() {
+            return Err(FsaError::EmptyLanguage);
+        }
+
+        Ok(Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_normalize(self.fsa.borrow()) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        })
+    }
+
+    // checks if the spectral radius of the matrix M of transition weights is below one;
+    // this holds iff the maximum row sum of M^k is below one for some k
+    fn converges(&self) -> bool {
+        let carcs = unsafe { fsa_to_arc_list(self.fsa.borrow()) };
+        let carcs = carcs.as_slice::<fsa_arc>();
+
+        // the row sums of M^k are scaled to a maximum of one,
+        // the logarithm of the maximum row sum is accumulated separately
+        let mut row_sums = vec![1f64; self.num_states()];
+        let mut log_norm = 0f64;
+        for _ in 0..CONVERGENCE_STEPS {
+            let mut next_row_sums = vec![0f64; row_sums.len()];
+            for carc in carcs {
+                next_row_sums[carc.from_state as usize] +=
+                    (-f64::from(carc.weight)).exp() * row_sums[carc.to_state as usize];
+            }
+
+            let norm = next_row_sums.iter().cloned().fold(0f64, f64::max);
+            log_norm += norm.ln();
+            // M^k = 0 if the Automaton is acyclic
+            if norm == 0.0 || log_norm < 0.0 {
+                return true;
+            }
+            row_sums = next_row_sums.into_iter().map(|sum| sum / norm).collect();
+        }
+
+        false
+    }
+
+    /// Epsilon-removal of an `Automaton`.
+    /// Returns an `Automaton` without epsilon transitions that recognizes
+    /// the same weighted language; the weights of epsilon paths are folded into
+    /// the remaining transitions.
+    pub fn remove_epsilon(&self) -> Self {
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_remove_epsilon(self.fsa.borrow()) }),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        }
+    }
+}
+
+impl<T, W> Automaton<T, W>
+where
+    T: Hash + Eq + Display + Clone,
+    W: Semiring + Display,
+{
+    /// Dump the symbol table to tab seperated values.
+    pub fn write_symbols<F>(&self, writer: &mut F) -> io::Result<()>
+    where
+        F: io::Write,
+    {
+        write_symbol_table(Borrow::<HashIntegeriser<T>>::borrow(&self.labels), writer)
+    }
+
+    /// Dump an `Automaton` as a Graphviz digraph.
+    /// States are drawn as circles, final states as double circles, and the initial
+    /// state has a dangling incoming arrow.
+    /// Each transition is an edge labeled with `label / weight`, where the weight is
+    /// shown as probability.
+    pub fn to_dot<F>(&self, writer: &mut F) -> io::Result<()>
+    where
+        F: io::Write,
+    {
+        let (arcs, q0, qfs) = self.clone().into_arcs();
+        let states = self.num_states();
+
+        writeln!(writer, "digraph {{")?;
+        writeln!(writer, "\trankdir=LR;")?;
+        for state in 0..states {
+            let shape = if qfs.contains(&state) {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            writeln!(writer, "\t{} [shape={}];", state, shape)?;
+        }
+        if q0 < states {
+            writeln!(writer, "\tinitial [shape=none, label=\"\"];")?;
+            writeln!(writer, "\tinitial -> {};", q0)?;
+        }
+        for arc in arcs {
+            let label = format!("{}", arc.label)
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"");
+            writeln!(
+                writer,
+                "\t{} -> {} [label=\"{} / {}\"];",
+                arc.from,
+                arc.to,
+                label,
+                arc.weight
+            )?;
+        }
+        writeln!(writer, "}}")
+    }
+}
+
+impl<A, W> Automaton<A, W>
+where
+    A: Hash + Eq + Clone,
+    W: Semiring,
+{
+    // integerizes the states and the label of a transition
+    // zero is reserved for epsilon
+    pub(crate) fn integerise_arc<Q, L>(
+        arc: Arc<Q, L, W>,
+        i_states: &mut HashIntegeriser<Q>,
+        i_labels: &mut HashIntegeriser<A>,
+    ) -> Result<fsa_arc, FsaError>
+    where
+        Q: Hash + Eq + Clone,
+        L: Into<Label<A>>,
+    {
+        let Arc {
+            from,
+            to,
+            label,
+            weight,
+        } = arc;
+
+        Ok(fsa_arc {
+            from_state: checked_id(i_states.integerise(from))?,
+            to_state: checked_id(i_states.integerise(to))?,
+            label: match label.into() {
+                Label::Epsilon => 0,
+                Label::Symbol(symbol) => checked_id(i_labels.integerise(symbol) + 1)?,
+            },
+            weight: weight.to_openfst(),
+        })
+    }
+
+    // constructs a FSA with integerized transition labels
+    // uses an existing integerizer to unify labels
+    // fails if there are more states or labels than integers in OpenFst
+    fn from_arcs_with_labels<Q, L, I>(
+        initial_state: Q,
+        final_states: Vec<(Q, W)>,
+        arcs: I,
+        i_labels: &mut HashIntegeriser<A>,
+    ) -> Result<fsa_t, FsaError>
+    where
+        Q: Hash + Eq + Clone,
+        L: Into<Label<A>>,
+        I: IntoIterator<Item = Arc<Q, L, W>>,
+    {
+        let mut i_states = HashIntegeriser::new();
+
+        // ensure initial state = 0, final state in i_states
+        i_states.integerise(initial_state);
+        let mut qfs = Vec::new();
+        for (final_state, weight) in final_states {
+            qfs.push(fsa_final {
+                state: checked_id(i_states.integerise(final_state))?,
+                weight: weight.to_openfst(),
+            });
+        }
+
+        let mut carcs: Vec<fsa_arc> = Vec::new();
+        for arc in arcs {
+            carcs.push(Automaton::integerise_arc(arc, &mut i_states, i_labels)?);
+        }
+
+        let states = checked_id(i_states.size())?;
+        Ok(unsafe {
+            fsa_from_arc_list(states, &*vec_t::new(&mut qfs), &*vec_t::new(&mut carcs))
+        })
+    }
+
+    /// Default constructor for an `Automaton`.
+    /// Consumes a list of `Arc` transitions and stores all labels
+    /// of type `A` in an `Integerizer<A>`.
+    /// The original states of type `Q` are lost after integerization.
+    /// Panics if there are more states or labels than integers in OpenFst,
+    /// see `try_from_arcs`.
+    pub fn from_arcs<Q>(
+        initial_state: Q,
+        final_state: Vec<Q>,
+        arcs: Vec<Arc<Q, A, W>>,
+    ) -> Automaton<A, W>
+    where
+        Q: Hash + Eq + Clone,
+    {
+        Automaton::from_arc_iter(initial_state, final_state, arcs)
+    }
+
+    /// Constructor for an `Automaton` like `from_arcs` that validates the weights
+    /// of the transitions.
+    /// A transition with weight zero, or a weight whose representation in OpenFst
+    /// is not finite, would silently remove all runs that use it from the language.
+    /// Fails with the position of the first such transition in `arcs`, or
+    /// with `FsaError::IdOverflow` if there are more states or labels than integers
+    /// in OpenFst.
+    pub fn try_from_arcs<Q>(
+        initial_state: Q,
+        final_state: Vec<Q>,
+        arcs: Vec<Arc<Q, A, W>>,
+    ) -> Result<Automaton<A, W>, FsaError>
+    where
+        Q: Hash + Eq + Clone,
+    {
+        if let Some(position) = arcs
+            .iter()
+            .position(|arc| !arc.weight.to_openfst().is_finite())
+        {
+            return Err(FsaError::InvalidArc(position));
+        }
+
+        let mut integeriser = HashIntegeriser::new();
+        let fsa = Automaton::from_arcs_with_labels(
+            initial_state,
+            final_state.into_iter().map(|q| (q, W::one())).collect(),
+            arcs,
+            &mut integeriser,
+        )?;
+
+        Ok(Automaton {
+            fsa: sync::Arc::new(fsa),
+            labels: sync::Arc::new(integeriser),
+            weights: PhantomData,
+        })
+    }
+
+    /// Constructor for an `Automaton` whose final states have weights.
+    /// It behaves like `from_arcs`, where each final state has weight one.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn from_arcs_weighted<Q>(
+        initial_state: Q,
+        final_states: Vec<(Q, W)>,
+        arcs: Vec<Arc<Q, A, W>>,
+    ) -> Automaton<A, W>
+    where
+        Q: Hash + Eq + Clone,
+    {
+        let mut integeriser = HashIntegeriser::new();
+        let fsa = sync::Arc::new(Automaton::from_arcs_with_labels(
+            initial_state,
+            final_states,
+            arcs,
+            &mut integeriser,
+        ).unwrap());
+
+        Automaton {
+            fsa,
+            labels: sync::Arc::new(integeriser),
+            weights: PhantomData,
+        }
+    }
+
+    /// Constructor for an `Automaton` that recognizes exactly one word with weight one.
+    /// Its states form a chain of a transition per symbol of `word`.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn from_word(word: &[A]) -> Automaton<A, W> {
+        let arcs = word.iter().cloned().enumerate().map(|(i, label)| Arc {
+            from: i,
+            to: i + 1,
+            label,
+            weight: W::one(),
+        });
+
+        Automaton::from_arc_iter(0, vec![word.len()], arcs)
+    }
+
+    /// Constructor for an `Automaton` that recognizes a finite set of weighted words.
+    /// The `Automaton` is a trie, i.e. words with a common prefix share the states
+    /// and transitions of that prefix; the weight of each word is the weight of its
+    /// final state.
+    /// If a word occurs several times, its last weight is used.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn from_words<I>(words: I) -> Automaton<A, W>
+    where
+        I: IntoIterator<Item = (Vec<A>, W)>,
+    {
+        // the state for each prefix, 0 for the empty prefix
+        let mut children: HashMap<(usize, A), usize> = HashMap::new();
+        let mut arcs = Vec::new();
+        let mut final_states = Vec::new();
+        for (word, weight) in words {
+            let mut state = 0;
+            for label in word {
+                let next_state = children.len() + 1;
+                state = *children
+                    .entry((state, label.clone()))
+                    .or_insert_with(|| {
+                        arcs.push(Arc {
+                            from: state,
+                            to: next_state,
+                            label,
+                            weight: W::one(),
+                        });
+                        next_state
+                    });
+            }
+            final_states.push((state, weight));
+        }
+
+        Automaton::from_arcs_weighted(0, final_states, arcs)
+    }
+
+    /// Constructor for an `Automaton` from any iterator over `Arc` transitions.
+    /// It behaves like `from_arcs`, but the transitions are integerized
+    /// while they are consumed instead of being collected before.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn from_arc_iter<Q, I>(initial_state: Q, final_state: Vec<Q>, arcs: I) -> Automaton<A, W>
+    where
+        Q: Hash + Eq + Clone,
+        I: IntoIterator<Item = Arc<Q, A, W>>,
+    {
+        // start with one per default, since zero is reserved for epsilon
+        let mut integeriser = HashIntegeriser::new();
+        let fsa = sync::Arc::new(Automaton::from_arcs_with_labels(
+            initial_state,
+            final_state.into_iter().map(|q| (q, W::one())).collect(),
+            arcs,
+            &mut integeriser,
+        ).unwrap());
+
+        Automaton {
+            fsa,
+            labels: sync::Arc::new(integeriser),
+            weights: PhantomData,
+        }
+    }
+
+    /// Constructor for an `Automaton` with epsilon transitions.
+    /// Consumes a list of `Arc` transitions whose labels are either `Label::Epsilon`
+    /// or `Label::Symbol`, otherwise it behaves like `from_arcs`.
+    /// It recovers an `Automaton` from the `Arc`s listed by `into_arcs`.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn from_arcs_with_epsilons<Q>(
+        initial_state: Q,
+        final_state: Vec<Q>,
+        arcs: Vec<Arc<Q, Label<A>, W>>,
+    ) -> Automaton<A, W>
+    where
+        Q: Hash + Eq + Clone,
+    {
+        Automaton::from_arcs_with_epsilons_weighted(
+            initial_state,
+            final_state.into_iter().map(|q| (q, W::one())).collect(),
+            arcs,
+        )
+    }
+
+    /// Constructor for an `Automaton` with epsilon transitions whose final states
+    /// have weights, like `from_arcs_with_epsilons` and `from_arcs_weighted`.
+    /// It recovers an `Automaton` from the `Arc`s listed by `into_arcs_weighted`.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn from_arcs_with_epsilons_weighted<Q>(
+        initial_state: Q,
+        final_states: Vec<(Q, W)>,
+        arcs: Vec<Arc<Q, Label<A>, W>>,
+    ) -> Automaton<A, W>
+    where
+        Q: Hash + Eq + Clone,
+    {
+        let mut integeriser = HashIntegeriser::new();
+        let fsa = sync::Arc::new(Automaton::from_arcs_with_labels(
+            initial_state,
+            final_states,
+            arcs,
+            &mut integeriser,
+        ).unwrap());
+
+        Automaton {
+            fsa,
+            labels: sync::Arc::new(integeriser),
+            weights: PhantomData,
+        }
+    }
+
+    /// Alternative constructor for an `Automaton`.
+    /// Synchronizes label integerization using the labels of an existing
+    /// `Automaton` and consumes a `Vec`tor of `Arc`s like `from_arcs`.
+    /// The `Integerizer` storing the labels of the first `Automaton`
+    /// is expanded to store the labels of both Automata.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn from_arcs_with_same_labels<Q>(
+        &self,
+        initial_state: Q,
+        final_state: Vec<Q>,
+        arcs: Vec<Arc<Q, A, W>>,
+    ) -> Automaton<A, W>
+    where
+        Q: Hash + Eq + Clone,
+    {
+        let mut integeriser = (*self.labels).clone();
+        let fsa = sync::Arc::new(Automaton::from_arcs_with_labels(
+            initial_state,
+            final_state.into_iter().map(|q| (q, W::one())).collect(),
+            arcs,
+            &mut integeriser,
+        ).unwrap());
+
+        Automaton {
+            fsa,
+            labels: sync::Arc::new(integeriser),
+            weights: PhantomData,
+        }
+    }
+
+    /// Alternative constructor for an `Automaton` like `from_arcs` whose labels are
+    /// integerized by an existing table, e.g. one that is shared among several Automata.
+    /// Automata that share a table are combined, e.g. by `intersect`, without relabeling.
+    /// Labels that are missing in the table are appended to a copy of it, which is used
+    /// by the resulting `Automaton`; the integers of the other labels are kept.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn from_arcs_with_integeriser<Q>(
+        integeriser: sync::Arc<HashIntegeriser<A>>,
+        initial_state: Q,
+        final_state: Vec<Q>,
+        arcs: Vec<Arc<Q, A, W>>,
+    ) -> Automaton<A, W>
+    where
+        Q: Hash + Eq + Clone,
+    {
+        let mut extended = (*integeriser).clone();
+        let fsa = sync::Arc::new(Automaton::from_arcs_with_labels(
+            initial_state,
+            final_state.into_iter().map(|q| (q, W::one())).collect(),
+            arcs,
+            &mut extended,
+        ).unwrap());
+
+        // the table is only replaced if labels were appended
+        let labels = if extended.size() == integeriser.size() {
+            integeriser
+        } else {
+            sync::Arc::new(extended)
+        };
+
+        Automaton {
+            fsa,
+            labels,
+            weights: PhantomData,
+        }
+    }
+
+    /// Read an `Automaton` from a file in the AT&T text format of OpenFst.
+    /// Each line contains either a transition `from to label [weight]`
+    /// or a final state `state [weight]`; the first state in the file is the initial state.
+    /// Labels are the integers of `symbols` as written by `write_symbols`,
+    /// where 0 is epsilon.
+    /// Weights are negative logarithms of probabilities, the default is 0.
+    /// Fails with `io::ErrorKind::InvalidData` for malformed lines and unknown labels.
+    pub fn read_att<R>(
+        symbols: sync::Arc<HashIntegeriser<A>>,
+        reader: R,
+    ) -> io::Result<Automaton<A, W>>
+    where
+        R: io::Read,
+    {
+        let mut i_states: HashIntegeriser<usize> = HashIntegeriser::new();
+        let mut qfs: Vec<fsa_final> = Vec::new();
+        let mut carcs: Vec<fsa_arc> = Vec::new();
+
+        for (line_number, line) in io::BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let invalid = |reason: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", line_number + 1, reason),
+                )
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            let mut states = Vec::new();
+            for field in fields.iter().take(if fields.len() > 2 { 2 } else { 1 }) {
+                let state = field.parse().map_err(|_| invalid("malformed state"))?;
+                let state = checked_id(i_states.integerise(state))
+                    .map_err(|_| invalid("too many states"))?;
+                states.push(state);
+            }
+            let weight: c_float = match fields.len() {
+                2 | 4 => fields[fields.len() - 1]
+                    .parse()
+                    .map_err(|_| invalid("malformed weight"))?,
+                _ => 0.0,
+            };
+
+            match fields.len() {
+                0 => (),
+                1 | 2 => qfs.push(fsa_final {
+                    state: states[0],
+                    weight,
+                }),
+                3 | 4 => {
+                    let label: usize = fields[2].parse().map_err(|_| invalid("malformed label"))?;
+                    if label > symbols.size() {
+                        return Err(invalid("unknown label"));
+                    }
+                    carcs.push(fsa_arc {
+                        from_state: states[0],
+                        to_state: states[1],
+                        label: checked_id(label).map_err(|_| invalid("unknown label"))?,
+                        weight,
+                    });
+                }
+                _ => return Err(invalid("expected a transition or a final state")),
+            }
+        }
+
+        // an empty file describes an automaton with an empty language
+        if i_states.size() == 0 {
+            i_states.integerise(0);
+        }
+
+        let fsa = unsafe {
+            fsa_from_arc_list(
+                // each state was checked when it was read
+                i_states.size() as c_int,
+                &*vec_t::new(&mut qfs),
+                &*vec_t::new(&mut carcs),
+            )
+        };
+
+        Ok(Automaton {
+            fsa: sync::Arc::new(fsa),
+            labels: symbols,
+            weights: PhantomData,
+        })
+    }
+
+    // computes an fsa whose labels are consistent with `i_labels`
+    // labels that are missing in `i_labels` are added
+    // fails if there are more labels than integers in OpenFst
+    fn fsa_with_labels(
+        &self,
+        i_labels: &mut HashIntegeriser<A>,
+    ) -> Result<sync::Arc<fsa_t>, FsaError> {
+        // zero is reserved for epsilon
+        let mut label_map: Vec<c_int> = vec![0];
+        for label_id in 0..(self.labels.size()) {
+            let label = self.labels.find_value(label_id).unwrap().clone();
+            label_map.push(checked_id(i_labels.integerise(label) + 1)?);
+        }
+
+        if label_map.iter().enumerate().all(|(i, &l)| i == l as usize) {
+            Ok(sync::Arc::clone(&self.fsa))
+        } else {
+            let label_map = vec_t::new(&mut label_map);
+            Ok(sync::Arc::new(unsafe { fsa_relabel(self.fsa.borrow(), &*label_map) }))
+        }
+    }
+
+    /// Relabels an `Automaton` such that its labels are integerized like in `target`.
+    /// Returns an `Automaton` that recognizes the same weighted language;
+    /// labels that are missing in `target` are appended to a copy of `target`,
+    /// which is used by the resulting `Automaton`.
+    /// Binary operations such as `intersect` unify the labels of their operands this way.
+    /// Panics if there are more labels than integers in OpenFst.
+    pub fn relabel(&self, target: &HashIntegeriser<A>) -> Self {
+        let mut labels = target.clone();
+        let fsa = self.fsa_with_labels(&mut labels).unwrap();
+
+        Automaton {
+            fsa,
+            labels: sync::Arc::new(labels),
+            weights: PhantomData,
+        }
+    }
+
+    /// Removes the transitions of an `Automaton` that do not satisfy a predicate,
+    /// e.g. those with a certain label.
+    /// Returns an `Automaton` that is trimmed using `connect` afterwards, since some
+    /// states may not be on any accepting path anymore.
+    /// Epsilon transitions are always kept.
+    pub fn retain_arcs<F>(&self, keep: F) -> Self
+    where
+        F: Fn(&Arc<usize, A, W>) -> bool,
+    {
+        let carcs = unsafe { fsa_to_arc_list(self.fsa.borrow()) };
+        let mut mask: Vec<c_int> = carcs
+            .as_slice::<fsa_arc>()
+            .iter()
+            .map(|carc| {
+                let arc = self.decode_arc(carc);
+                let kept = match arc.label {
+                    Label::Epsilon => true,
+                    Label::Symbol(label) => keep(&Arc {
+                        from: arc.from,
+                        to: arc.to,
+                        label,
+                        weight: arc.weight,
+                    }),
+                };
+                kept as c_int
+            })
+            .collect();
+
+        let fsa = unsafe { fsa_retain_arcs(self.fsa.borrow(), &*vec_t::new(&mut mask)) };
+        Automaton {
+            fsa: sync::Arc::new(fsa),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        }.connect()
+    }
+
+    /// Applies a function to each label of an `Automaton`, e.g. to map words to their
+    /// part-of-speech tags.
+    /// Returns an `Automaton` with the same states and transitions whose labels
+    /// are integerized anew; if several labels are mapped to the same one,
+    /// the resulting `Automaton` may not be deterministic.
+    /// Panics if there are more labels than integers in OpenFst.
+    pub fn map_labels<B, F>(&self, f: F) -> Automaton<B, W>
+    where
+        B: Hash + Eq + Clone,
+        F: Fn(&A) -> B,
+    {
+        let mut integeriser = HashIntegeriser::new();
+        // zero is reserved for epsilon
+        let mut label_map: Vec<c_int> = vec![0];
+        for label_id in 0..(self.labels.size()) {
+            let label = f(self.labels.find_value(label_id).unwrap());
+            label_map.push(checked_id(integeriser.integerise(label) + 1).unwrap());
+        }
+
+        let fsa = unsafe { fsa_relabel(self.fsa.borrow(), &*vec_t::new(&mut label_map)) };
+        Automaton {
+            fsa: sync::Arc::new(fsa),
+            labels: sync::Arc::new(integeriser),
+            weights: PhantomData,
+        }
+    }
+
+    // unifies the labels of both Automata like `from_arcs_with_same_labels`
+    // returns the unified labels and the fsa of `other` using them
+    // fails if there are more labels than integers in OpenFst
+    #[allow(clippy::type_complexity)]
+    fn unify_labels(
+        &self,
+        other: &Automaton<A, W>,
+    ) -> Result<(sync::Arc<HashIntegeriser<A>>, sync::Arc<fsa_t>), FsaError> {
+        if sync::Arc::ptr_eq(&self.labels, &other.labels) {
+            Ok((sync::Arc::clone(&self.labels), sync::Arc::clone(&other.fsa)))
+        } else {
+            let mut integeriser = (*self.labels).clone();
+            let fsa = other.fsa_with_labels(&mut integeriser)?;
+            Ok((sync::Arc::new(integeriser), fsa))
+        }
+    }
+
+    /// Hadarmard product of two Automata.
+    /// Returns an `Automaton` whose language contains
+    /// the intersection of both Automata's languages.
+    /// An empty intersection is not an error; it fails only if OpenFst
+    /// could not compute the product, or with `FsaError::IdOverflow` if there are
+    /// more labels than integers in OpenFst.
+    /// The labels of both Automata are unified like in `from_arcs_with_same_labels`.
+    pub fn intersect(&self, other: &Automaton<A, W>) -> Result<Self, FsaError> {
+        let (labels, other_fsa) = self.unify_labels(other)?;
+        let fsa = unsafe { fsa_intersect(self.fsa.borrow(), other_fsa.borrow()) };
+        Automaton::checked(fsa, labels, "intersection")
+    }
+
+    /// Hadamard product of an `Automaton` and a sequence of Automata.
+    /// Returns an `Automaton` like folding `intersect`, but the labels of all Automata are
+    /// unified only once and each intermediate product is trimmed by `connect`.
+    /// Since the product is associative, the order of the Automata does not change
+    /// the weighted language.
+    /// Fails if OpenFst could not compute one of the products.
+    pub fn intersect_all<I>(self, others: I) -> Result<Self, FsaError>
+    where
+        I: IntoIterator<Item = Automaton<A, W>>,
+    {
+        // the labels of this Automaton keep their integers
+        let mut integeriser = (*self.labels).clone();
+        let fsas: Vec<sync::Arc<fsa_t>> = others
+            .into_iter()
+            .map(|other| other.fsa_with_labels(&mut integeriser))
+            .collect::<Result<_, _>>()?;
+        let labels = sync::Arc::new(integeriser);
+
+        let mut product = Automaton {
+            fsa: self.fsa,
+            labels: sync::Arc::clone(&labels),
+            weights: PhantomData,
+        };
+        for fsa in fsas {
+            let next = unsafe { fsa_intersect(product.fsa.borrow(), fsa.borrow()) };
+            product = Automaton::checked(next, sync::Arc::clone(&labels), "intersection")?.connect();
+        }
+        Ok(product)
+    }
+
+    /// Hadamard product of two Automata like `intersect`, but sorts the transitions
+    /// of both Automata by `SortKey::ILabel` before.
+    /// OpenFst matches the labels of sorted transitions without searching all
+    /// transitions of a state, and the product does not fail for Automata that were
+    /// sorted by `SortKey::State` before.
+    /// Recognizes the same weighted language as `intersect`.
+    pub fn intersect_sorted(&self, other: &Automaton<A, W>) -> Result<Self, FsaError> {
+        self.arc_sort(SortKey::ILabel)
+            .intersect(&other.arc_sort(SortKey::ILabel))
+    }
+
+    /// Hadamard product with inverted automaton.
+    /// Returns an `Automaton` whose
+    /// language contains the words contained in the language
+    /// of the first `Automaton` minus the words contained in the language of the second one.
+    /// Fails if OpenFst could not compute the difference, or with
+    /// `FsaError::IdOverflow` if there are more labels than integers in OpenFst.
+    /// The labels of both Automata are unified like in `from_arcs_with_same_labels`;
+    /// words with labels that only occur in the second `Automaton` are not
+    /// in the first language, so they do not affect the difference.
+    pub fn difference(&self, other: &Automaton<A, W>) -> Result<Self, FsaError> {
+        let (labels, other_fsa) = self.unify_labels(other)?;
+        let fsa = unsafe { fsa_difference(self.fsa.borrow(), other_fsa.borrow()) };
+        Automaton::checked(fsa, labels, "difference")
+    }
+
+    /// Encodes the label and weight of each transition of an `Automaton` as a single
+    /// label, see `EncodeTable`.
+    /// The final weights are moved to transitions into a new final state.
+    pub fn encode(&self) -> (Automaton<usize, W>, EncodeTable<A, W>) {
+        EncodeTable::encode(self)
+    }
+
+    /// Multiplies the weight of each transition and final state of an `Automaton`
+    /// with the weight of the same transition or final state of another `Automaton`.
+    /// Both Automata must have the same structure, i.e. the same initial and final
+    /// states and the same transitions in the same order as listed by
+    /// `into_arcs`, e.g. if one is obtained from the other by `map_weights`.
+    /// In contrast to `intersect`, the states and transitions are not changed.
+    /// Fails with `FsaError::DifferentStructure` otherwise.
+    pub fn reweight(&self, weights: &Automaton<A, W>) -> Result<Self, FsaError> {
+        let (arcs, q0, _) = self.clone().into_arcs();
+        let (arcs_, q0_, _) = weights.clone().into_arcs();
+        let finals = self.final_weights();
+        let finals_ = weights.final_weights();
+
+        let same_structure = q0 == q0_
+            && arcs.len() == arcs_.len()
+            && finals.len() == finals_.len()
+            && arcs
+                .iter()
+                .zip(&arcs_)
+                .all(|(arc, arc_)| (arc.from, arc.to, &arc.label) == (arc_.from, arc_.to, &arc_.label))
+            && finals.iter().zip(&finals_).all(|(qf, qf_)| qf.0 == qf_.0);
+        if !same_structure {
+            return Err(FsaError::DifferentStructure);
+        }
+
+        let mut arc_weights: Vec<c_float> = arcs
+            .into_iter()
+            .zip(arcs_)
+            .map(|(arc, arc_)| (arc.weight * arc_.weight).to_openfst())
+            .collect();
+        let mut final_weights: Vec<fsa_final> = finals
+            .into_iter()
+            .zip(finals_)
+            .map(|((state, weight), (_, weight_))| {
+                Ok(fsa_final {
+                    state: checked_id(state)?,
+                    weight: (weight * weight_).to_openfst(),
+                })
+            })
+            .collect::<Result<_, FsaError>>()?;
+
+        let fsa = unsafe {
+            fsa_reweight(
+                self.fsa.borrow(),
+                &*vec_t::new(&mut arc_weights),
+                &*vec_t::new(&mut final_weights),
+            )
+        };
+        Ok(Automaton {
+            fsa: sync::Arc::new(fsa),
+            labels: sync::Arc::clone(&self.labels),
+            weights: PhantomData,
+        })
+    }
+
+    /// Union of two Automata.
+    /// Returns an `Automaton` whose language contains the words of both
+    /// Automata's languages.
+    /// A word contained in both languages is recognized by two runs whose weights add up.
+    /// The labels of both Automata are unified like in `from_arcs_with_same_labels`.
+    /// Panics if there are more labels than integers in OpenFst.
+    pub fn union(&self, other: &Automaton<A, W>) -> Self {
+        let (labels, other_fsa) = self.unify_labels(other).unwrap();
+
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_union(self.fsa.borrow(), other_fsa.borrow()) }),
+            labels,
+            weights: PhantomData,
+        }
+    }
+
+    /// Concatenation of two Automata.
+    /// Returns an `Automaton` whose language contains each word of the first
+    /// `Automaton`'s language followed by each word of the second `Automaton`'s language;
+    /// the weights of both words are multiplied.
+    /// If one of the languages is empty, so is the resulting language.
+    /// The labels of both Automata are unified like in `from_arcs_with_same_labels`.
+    /// Panics if there are more labels than integers in OpenFst.
+    pub fn concat(&self, other: &Automaton<A, W>) -> Self {
+        let (labels, other_fsa) = self.unify_labels(other).unwrap();
+
+        Automaton {
+            fsa: sync::Arc::new(unsafe { fsa_concat(self.fsa.borrow(), other_fsa.borrow()) }),
+            labels,
+            weights: PhantomData,
+        }
+    }
+
+    // combines a list of Automata using a function of OpenFst
+    // the labels of all Automata are unified once
+    // panics if there are more labels than integers in OpenFst
+    fn combine_all<I>(parts: I, combine: unsafe extern "C" fn(*const vec_t) -> fsa_t) -> Self
+    where
+        I: IntoIterator<Item = Automaton<A, W>>,
+    {
+        let parts: Vec<Automaton<A, W>> = parts.into_iter().collect();
+        let mut integeriser = parts
+            .first()
+            .map(|part| (*part.labels).clone())
+            .unwrap_or_else(HashIntegeriser::new);
+        let fsas: Vec<sync::Arc<fsa_t>> = parts
+            .iter()
+            .map(|part| part.fsa_with_labels(&mut integeriser).unwrap())
+            .collect();
+
+        let mut fsa_ptrs: Vec<*const fsa_t> = fsas.iter()
+            .map(|fsa| fsa.borrow() as *const fsa_t)
+            .collect();
+        Automaton {
+            fsa: sync::Arc::new(unsafe { combine(&*vec_t::new(&mut fsa_ptrs)) }),
+            labels: sync::Arc::new(integeriser),
+            weights: PhantomData,
+        }
+    }
+
+    /// Concatenation of a sequence of Automata.
+    /// Returns an `Automaton` like folding `concat`, but the labels of all Automata are
+    /// unified only once and the Automata are concatenated in a single pass.
+    /// The concatenation of no Automata recognizes only the empty word.
+    /// Panics if there are more labels than integers in OpenFst.
+    pub fn concat_all<I>(parts: I) -> Self
+    where
+        I: IntoIterator<Item = Automaton<A, W>>,
+    {
+        Automaton::combine_all(parts, fsa_concat_all)
+    }
+
+    /// Union of a sequence of Automata.
+    /// Returns an `Automaton` like folding `union`, but the labels of all Automata are
+    /// unified only once and a single new initial state leads to each `Automaton`.
+    /// The union of no Automata recognizes the empty language.
+    /// Panics if there are more labels than integers in OpenFst.
+    pub fn union_all<I>(parts: I) -> Self
+    where
+        I: IntoIterator<Item = Automaton<A, W>>,
+    {
+        Automaton::combine_all(parts, fsa_union_all)
+    }
+
+    /// Returns the word of the best run in an `Automaton`, i.e. the most probable
+    /// or the least costly one, together with its weight,
+    /// or `None` if the language is empty.
+    pub fn best_path(&self) -> Option<(Vec<A>, W)> {
+        let nbest = self.n_best_automaton(1, false);
+        let qfs = nbest.final_weights();
+        let (arcs, q0, _) = nbest.into_arcs();
+        if qfs.is_empty() {
+            None
+        } else {
+            language(arcs, q0, qfs).next()
+        }
+    }
+
+    /// Returns the words of up to `n` best runs in an `Automaton`
+    /// with their weights, sorted from the best to the worst weight.
+    /// Words with equal weights are ordered by the integers of their labels.
+    pub fn n_best(&self, n: usize) -> Vec<(Vec<A>, W)> {
+        self.sorted_words(self.n_best_automaton(n, false))
+    }
+
+    /// Returns up to `n` best distinct words in an `Automaton` like `n_best`.
+    /// Each word is listed once with the weight of its best run, even if it
+    /// is recognized by several runs.
+    pub fn n_best_unique(&self, n: usize) -> Vec<(Vec<A>, W)> {
+        self.sorted_words(self.n_best_automaton(n, true))
+    }
+
+    // lists the words of an n-best automaton from the best to the worst weight
+    fn sorted_words(&self, nbest: Self) -> Vec<(Vec<A>, W)> {
+        let qfs = nbest.final_weights();
+        let (arcs, q0, _) = nbest.into_arcs();
+        if qfs.is_empty() {
+            return Vec::new();
+        }
+
+        // OpenFst's representation orders weights from the best to the worst
+        let mut words: Vec<(c_float, Vec<usize>, Vec<A>, W)> = language(arcs, q0, qfs)
+            .map(|(word, weight)| {
+                let label_ids = word.iter()
+                    .map(|label| self.labels.find_key(label).unwrap())
+                    .collect();
+                (weight.to_openfst(), label_ids, word, weight)
+            })
+            .collect();
+        words.sort_by(|(w1, l1, _, _), (w2, l2, _, _)| {
+            w1.partial_cmp(w2)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| l1.cmp(l2))
+        });
+
+        words
+            .into_iter()
+            .map(|(_, _, word, weight)| (word, weight))
+            .collect()
+    }
+
+    // decodes an integerized transition, where 0 is epsilon
+    // fails if its label is unknown or its weight is invalid
+    fn try_decode_arc(&self, carc: &fsa_arc) -> Result<Arc<usize, Label<A>, W>, FsaError> {
+        let (from, to) = (carc.from_state as usize, carc.to_state as usize);
+        let label = match carc.label {
+            0 => Label::Epsilon,
+            label => Label::Symbol(
+                self.labels
+                    .find_value((label as usize).wrapping_sub(1))
+                    .ok_or(FsaError::UnknownLabel(label as usize))?
+                    .clone(),
+            ),
+        };
+        let weight = W::try_from_openfst(carc.weight).ok_or(FsaError::InvalidWeight(from, to))?;
+
+        Ok(Arc {
+            from,
+            to,
+            label,
+            weight,
+        })
+    }
+
+    // decodes an integerized transition
+    fn decode_arc(&self, carc: &fsa_arc) -> Arc<usize, Label<A>, W> {
+        self.try_decode_arc(carc).unwrap()
+    }
+
+    /// Iterates over the `Arc`s of an `Automaton`.
+    /// In contrast to `into_arcs`, the `Arc`s are not collected and
+    /// their labels are only looked up when they are yielded.
+    pub fn arcs<'a>(&'a self) -> impl Iterator<Item = Arc<usize, Label<A>, W>> + 'a {
+        let carcs = unsafe { fsa_to_arc_list(self.fsa.borrow()) };
+        let length = carcs.as_slice::<fsa_arc>().len();
+
+        (0..length).map(move |i| self.decode_arc(&carcs.as_slice::<fsa_arc>()[i]))
+    }
+
+    /// Iterates over the `Arc`s leaving a state of an `Automaton`.
+    /// Only the transitions of this state are looked up in OpenFst,
+    /// so it is cheaper than filtering `arcs`.
+    /// There are no `Arc`s leaving a state that does not exist.
+    pub fn arcs_from<'a>(
+        &'a self,
+        state: usize,
+    ) -> impl Iterator<Item = Arc<usize, Label<A>, W>> + 'a {
+        // states beyond the range of c_int do not exist
+        let state = checked_id(state).unwrap_or(-1);
+        let carcs = unsafe { fsa_arcs_from(self.fsa.borrow(), state) };
+        let length = carcs.as_slice::<fsa_arc>().len();
+
+        (0..length).map(move |i| self.decode_arc(&carcs.as_slice::<fsa_arc>()[i]))
+    }
+
+    /// Lists the `Arc`s of an `Automaton`.
+    /// Since the original type of states cannot be recovered, we use `usize`.
+    /// Each label is decoded either as `Label::Epsilon` or as `Label::Symbol`.
+    #[allow(clippy::type_complexity)]
+    pub fn into_arcs(self) -> (Vec<Arc<usize, Label<A>, W>>, usize, Vec<usize>) {
+        self.try_into_arcs().unwrap()
+    }
+
+    /// Lists the `Arc`s of an `Automaton` like `into_arcs`.
+    /// Fails instead of panicking if there is a transition with an unknown label,
+    /// e.g. after `read_binary` with a symbol table of another `Automaton`,
+    /// or with an invalid weight.
+    #[allow(clippy::type_complexity)]
+    pub fn try_into_arcs(
+        self,
+    ) -> Result<(Vec<Arc<usize, Label<A>, W>>, usize, Vec<usize>), FsaError> {
+        let carcs = unsafe { fsa_to_arc_list(self.fsa.borrow()) };
+        let arcs = carcs
+            .as_slice::<fsa_arc>()
+            .iter()
+            .map(|carc| self.try_decode_arc(carc))
+            .collect::<Result<_, _>>()?;
+
+        Ok((arcs, self.initial_state(), self.final_states()))
+    }
+
+    /// Lists the `Arc`s of an `Automaton` like `into_arcs`, but with the weights
+    /// of the final states as they are reported by `final_weights`.
+    /// In contrast to `into_arcs`, the `Automaton` is recovered by
+    /// `from_arcs_with_epsilons_weighted`.
+    #[allow(clippy::type_complexity)]
+    pub fn into_arcs_weighted(self) -> (Vec<Arc<usize, Label<A>, W>>, usize, Vec<(usize, W)>) {
+        let final_weights = self.final_weights();
+        let (arcs, q0, _) = self.into_arcs();
+        (arcs, q0, final_weights)
+    }
+
+    /// Hashes the structure of an `Automaton`, i.e. its initial state, its final states
+    /// with their weights and its transitions with their labels and weights,
+    /// regardless of the order of the transitions.
+    /// Automata with the same transitions between the same states have the same hash,
+    /// but Automata that recognize the same language may not;
+    /// e.g. `from_arcs` numbers the states in the order they occur.
+    pub fn structural_hash(&self) -> u64
+    where
+        A: Hash,
+    {
+        let (arcs, q0, _) = self.clone().into_arcs();
+        // each label is reduced to its hash, so that the transitions can be sorted
+        let mut arcs: Vec<(usize, usize, u64, u32)> = arcs
+            .into_iter()
+            .map(|arc| {
+                let mut label_hasher = DefaultHasher::new();
+                arc.label.hash(&mut label_hasher);
+                (arc.from, arc.to, label_hasher.finish(), arc.weight.to_openfst().to_bits())
+            })
+            .collect();
+        arcs.sort();
+
+        let mut hasher = DefaultHasher::new();
+        q0.hash(&mut hasher);
+        for (state, weight) in self.final_weights() {
+            (state, weight.to_openfst().to_bits()).hash(&mut hasher);
+        }
+        arcs.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Lists the `Arc`s of an `Automaton` from the best to the worst weight,
+    /// see `Arc::cmp_by_weight`.
+    pub fn sorted_arcs_by_weight(&self) -> Vec<Arc<usize, Label<A>, W>> {
+        let (mut arcs, _, _) = self.clone().into_arcs();
+        arcs.sort_by(Arc::cmp_by_weight);
+        arcs
+    }
+}
+
+// queries that sum up the weights of runs in the log semiring
+impl<A> Automaton<A>
+where
+    A: Hash + Eq + Clone,
+{
+    /// Checks if two Automata recognize the same weighted language.
+    /// Both Automata are normalized by removing epsilon transitions, determinization
+    /// and weight pushing before they are compared, so the `Automaton`s need to be
+    /// determinizable; weights are compared up to a small tolerance.
+    /// The labels of both Automata are unified like in `from_arcs_with_same_labels`.
+    /// Panics if there are more labels than integers in OpenFst.
+    pub fn equivalent(&self, other: &Automaton<A>) -> bool {
+        let (_, other_fsa) = self.unify_labels(other).unwrap();
+        unsafe { fsa_equivalent(self.fsa.borrow(), other_fsa.borrow()) != 0 }
+    }
+
+    /// Samples a run of an `Automaton`.
+    /// Each run is drawn with a probability proportional to its weight, i.e. its weight
+    /// divided by the sum of the weights of all runs, computed by `shortest_distance`.
+    /// Returns the word and the weight of the run, or `None` if the language is empty.
+    pub fn sample_path<R: Rng>(&self, rng: &mut R) -> Option<(Vec<A>, LogDomain<f32>)> {
+        let to_final = self.shortest_distance(true);
+        let finals: HashMap<usize, LogDomain<f32>> = self.final_weights().into_iter().collect();
+        let (arcs, q0, _) = self.clone().into_arcs();
+        let mut arcs_from: Vec<Vec<Arc<usize, Label<A>>>> = vec![Vec::new(); to_final.len()];
+        for arc in arcs {
+            arcs_from[arc.from].push(arc);
+        }
+
+        if q0 >= to_final.len() || to_final[q0].is_zero() {
+            return None;
+        }
+
+        let (mut state, mut word, mut weight) = (q0, Vec::new(), LogDomain::one());
+        loop {
+            // stop or take a transition proportionally to the weight of the remaining runs
+            let mut choice = rng.gen::<f32>() * to_final[state].value();
+            let stop = finals.get(&state).cloned().unwrap_or_else(LogDomain::zero);
+            if choice < stop.value() {
+                return Some((word, weight * stop));
+            }
+            choice -= stop.value();
+
+            let arc = {
+                let mut alive = arcs_from[state]
+                    .iter()
+                    .filter(|arc| !to_final[arc.to].is_zero());
+                let last = alive.clone().last();
+                alive
+                    .find(|arc| {
+                        let mass = (arc.weight * to_final[arc.to]).value();
+                        choice -= mass;
+                        choice < 0.0
+                    })
+                    .or(last)
+            };
+
+            match arc {
+                Some(arc) => {
+                    if let Label::Symbol(ref symbol) = arc.label {
+                        word.push(symbol.clone());
+                    }
+                    weight *= arc.weight;
+                    state = arc.to;
+                }
+                // only due to rounding errors
+                None => return Some((word, weight * stop)),
+            }
+        }
+    }
+
+    /// Weight of a word in the language of an `Automaton`.
+    /// Returns the sum of the weights of all accepting runs on the word, or
+    /// `None` if the word is not accepted or contains unknown labels.
+    /// Epsilon transitions are not followed.
+    pub fn weight_of(&self, word: &[A]) -> Option<LogDomain<f32>> {
+        self.weight_of_compiled(&self.compile_query(word)?)
+    }
+
+    /// Integerizes a word for repeated queries with `weight_of_compiled`.
+    /// Returns `None` if the word contains unknown labels.
+    pub fn compile_query(&self, word: &[A]) -> Option<CompiledWord<A>> {
+        let mut labels: Vec<c_int> = Vec::new();
+        self.integerise_query(word, &mut labels)?;
+
+        Some(CompiledWord {
+            labels,
+            symbols: sync::Arc::clone(&self.labels),
+        })
+    }
+
+    /// Weights of a list of words like `weight_of` for each of them.
+    /// The words are integerized into a single buffer that is shared by all queries.
+    pub fn weights_of_batch(&self, words: &[Vec<A>]) -> Vec<Option<LogDomain<f32>>> {
+        let mut labels: Vec<c_int> = Vec::with_capacity(
+            words.iter().map(Vec::len).max().unwrap_or(0)
+        );
+
+        words
+            .iter()
+            .map(|word| {
+                labels.clear();
+                self.integerise_query(word, &mut labels)?;
+                self.weight_of_labels(&mut labels)
+            })
+            .collect()
+    }
+
+    // appends the integers of the labels of a word to a buffer,
+    // None if the word contains unknown labels
+    fn integerise_query(&self, word: &[A], labels: &mut Vec<c_int>) -> Option<()> {
+        for label in word {
+            labels.push(checked_id(self.labels.find_key(label)? + 1).ok()?);
+        }
+        Some(())
+    }
+
+    // weight of an integerized word, None if it is not accepted
+    fn weight_of_labels(&self, labels: &mut Vec<c_int>) -> Option<LogDomain<f32>> {
+        let weight = unsafe { fsa_weight_of(self.fsa.borrow(), &*vec_t::new(labels)) };
+        if weight.is_infinite() {
+            None
+        } else {
+            Some(LogDomain::from_openfst(weight))
+        }
+    }
+
+    /// Weight of a word compiled by `compile_query` like `weight_of`.
+    /// Returns `None` unless the word was compiled by an `Automaton` that shares
+    /// the labels of this one, e.g. by this `Automaton` itself or by one constructed
+    /// with `from_arcs_with_integeriser` from the same labels.
+    pub fn weight_of_compiled(&self, word: &CompiledWord<A>) -> Option<LogDomain<f32>> {
+        if !sync::Arc::ptr_eq(&self.labels, &word.symbols) {
+            return None;
+        }
+        let mut labels = word.labels.clone();
+        self.weight_of_labels(&mut labels)
+    }
+}
+
+// writes each label of an integerizer with its integer increased by one
+// fails with io::ErrorKind::InvalidData instead of panicking if there is no
+// label for an integer
+fn write_symbol_table<I, F>(labels: &I, writer: &mut F) -> io::Result<()>
+where
+    I: Integeriser,
+    I::Item: Display,
+    F: io::Write,
+{
+    for label_id in 0..(labels.size()) {
+        match labels.find_value(label_id) {
+            Some(label) => writeln!(writer, "{}\t{}", label, label_id + 1)?,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("there is no label with the integer {}", label_id + 1),
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read a symbol table of tab seperated values as written by `Automaton::write_symbols`.
+/// Each line contains a label and its integer; since `write_symbols` starts with
+/// 1 (0 is reserved for epsilon), each integer is decreased by one.
+/// Fails with `io::ErrorKind::InvalidData` if a line is malformed or
+/// the integers are not consecutive.
+pub fn read_symbols<R>(reader: R) -> io::Result<HashIntegeriser<String>>
+where
+    R: io::Read,
+{
+    let mut symbols: Vec<(usize, String)> = Vec::new();
+    for (line_number, line) in io::BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let mut fields = line.rsplitn(2, '\t');
+        match (fields.next().map(str::parse::<usize>), fields.next()) {
+            (Some(Ok(label_id)), Some(label)) if label_id > 0 => {
+                symbols.push((label_id - 1, label.to_string()))
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected a label and a positive integer", line_number + 1),
+                ))
+            }
+        }
+    }
+    symbols.sort();
+
+    let mut integeriser = HashIntegeriser::new();
+    for (label_id, label) in symbols {
+        if integeriser.integerise(label) != label_id {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the integers of the labels are not consecutive at {}", label_id + 1),
+            ));
+        }
+    }
+    Ok(integeriser)
+}
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::de::Error as DeError;
+
+// fields of the human-readable representation of an Automaton
+const READABLE_FIELDS: &[&str] = &["version", "states", "initial", "finals", "arcs", "labels"];
+
+/// Human-readable formats, e.g. JSON, represent an `Automaton` by the number of its
+/// states, its initial state, its final states with their weights, its transitions
+/// `(from, to, label, weight)` and the list of its labels, where the label `l` of a
+/// transition is the `l`-th label in this list and 0 is epsilon.
+/// Weights are represented as in OpenFst.
+/// Other formats represent an `Automaton` by the binary string of OpenFst.
+impl<T, W> Serialize for Automaton<T, W>
+where
+    T: Serialize + Hash + Eq + Clone,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let states = unsafe { fsa_num_states(self.fsa.borrow()) };
+            let initial = if states == 0 {
+                0
+            } else {
+                unsafe { fsa_initial_state(self.fsa.borrow()) }
+            };
+            let finals: Vec<(c_int, c_float)> = unsafe { fsa_final_weights(self.fsa.borrow()) }
+                .as_slice::<fsa_final>()
+                .iter()
+                .map(|qf| (qf.state, qf.weight))
+                .collect();
+            let arcs: Vec<(c_int, c_int, c_int, c_float)> = unsafe {
+                fsa_to_arc_list(self.fsa.borrow())
+            }.as_slice::<fsa_arc>()
+                .iter()
+                .map(|carc| (carc.from_state, carc.to_state, carc.label, carc.weight))
+                .collect();
+            let labels: Vec<&T> = (0..self.labels.size())
+                .map(|label_id| self.labels.find_value(label_id).unwrap())
+                .collect();
+
+            let mut automaton = serializer.serialize_struct("Automaton", READABLE_FIELDS.len())?;
+            automaton.serialize_field("version", &SERIALIZATION_VERSION)?;
+            automaton.serialize_field("states", &states)?;
+            automaton.serialize_field("initial", &initial)?;
+            automaton.serialize_field("finals", &finals)?;
+            automaton.serialize_field("arcs", &arcs)?;
+            automaton.serialize_field("labels", &labels)?;
+            return automaton.end();
+        }
+
+        let &Automaton {
+            ref fsa,
+            ref labels,
+            ..
+        } = self;
+
+        (
+            SERIALIZATION_VERSION,
+            Borrow::<fsa_t>::borrow(fsa),
+            Borrow::<HashIntegeriser<T>>::borrow(labels),
+        ).serialize(serializer)
+    }
+}
+
+// deserializes the envelope of an Automaton, i.e. the version of the format,
+// the binary string of the fsa and the labels
+// the version is checked before anything else is deserialized
+struct AutomatonVisitor<T, W> {
+    automaton: PhantomData<(T, W)>,
+}
+
+impl<'de, T, W> Visitor<'de> for AutomatonVisitor<T, W>
+where
+    T: Deserialize<'de> + Hash + Eq + Clone,
+{
+    type Value = Automaton<T, W>;
+
+    fn expecting(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "a version, an fsa and its labels")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Automaton<T, W>, S::Error> {
+        let version: u32 = seq.next_element()?
+            .ok_or_else(|| S::Error::invalid_length(0, &self))?;
+        if version != SERIALIZATION_VERSION {
+            return Err(S::Error::custom(format!(
+                "unsupported version {} of a serialized automaton, expected version {}",
+                version, SERIALIZATION_VERSION
+            )));
+        }
+        let fsa: fsa_t = seq.next_element()?
+            .ok_or_else(|| S::Error::invalid_length(1, &self))?;
+        let labels: HashIntegeriser<T> = seq.next_element()?
+            .ok_or_else(|| S::Error::invalid_length(2, &self))?;
+
+        Ok(Automaton {
+            fsa: sync::Arc::new(fsa),
+            labels: sync::Arc::new(labels),
+            weights: PhantomData,
+        })
+    }
+}
+
+// deserializes the human-readable representation of an Automaton
+struct ReadableAutomatonVisitor<T, W> {
+    automaton: PhantomData<(T, W)>,
+}
+
+impl<'de, T, W> Visitor<'de> for ReadableAutomatonVisitor<T, W>
+where
+    T: Deserialize<'de> + Hash + Eq + Clone,
+{
+    type Value = Automaton<T, W>;
+
+    fn expecting(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "an automaton with the fields {}", READABLE_FIELDS.join(", "))
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Automaton<T, W>, M::Error> {
+        let mut version: Option<u32> = None;
+        let mut states: Option<c_int> = None;
+        let mut initial: Option<c_int> = None;
+        let mut finals: Option<Vec<(c_int, c_float)>> = None;
+        let mut arcs: Option<Vec<(c_int, c_int, c_int, c_float)>> = None;
+        let mut labels: Option<Vec<T>> = None;
+
+        while let Some(field) = map.next_key::<String>()? {
+            match field.as_str() {
+                "version" => version = Some(map.next_value()?),
+                "states" => states = Some(map.next_value()?),
+                "initial" => initial = Some(map.next_value()?),
+                "finals" => finals = Some(map.next_value()?),
+                "arcs" => arcs = Some(map.next_value()?),
+                "labels" => labels = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let version = version.ok_or_else(|| M::Error::missing_field("version"))?;
+        if version != SERIALIZATION_VERSION {
+            return Err(M::Error::custom(format!(
+                "unsupported version {} of a serialized automaton, expected version {}",
+                version, SERIALIZATION_VERSION
+            )));
+        }
+        let states = states.ok_or_else(|| M::Error::missing_field("states"))?;
+        let initial = initial.ok_or_else(|| M::Error::missing_field("initial"))?;
+        let finals = finals.ok_or_else(|| M::Error::missing_field("finals"))?;
+        let arcs = arcs.ok_or_else(|| M::Error::missing_field("arcs"))?;
+        let labels = labels.ok_or_else(|| M::Error::missing_field("labels"))?;
+
+        let mut integeriser = HashIntegeriser::new();
+        for label in labels {
+            integeriser.integerise(label);
+        }
+
+        // OpenFst starts in state 0, so it is swapped with the initial state
+        // the language of an FSA without states is empty
+        let states = states.max(1);
+        let state = |q: c_int| {
+            if q < 0 || q >= states {
+                Err(M::Error::custom(format!("the state {} does not exist", q)))
+            } else if q == initial {
+                Ok(0)
+            } else if q == 0 {
+                Ok(initial)
+            } else {
+                Ok(q)
+            }
+        };
+        state(initial)?;
+
+        let mut qfs = Vec::new();
+        for (q, weight) in finals {
+            qfs.push(fsa_final {
+                state: state(q)?,
+                weight,
+            });
+        }
+        let mut carcs = Vec::new();
+        for (from, to, label, weight) in arcs {
+            if label < 0 || label as usize > integeriser.size() {
+                return Err(M::Error::custom(format!("the label {} does not exist", label)));
+            }
+            carcs.push(fsa_arc {
+                from_state: state(from)?,
+                to_state: state(to)?,
+                label,
+                weight,
+            });
+        }
+
+        let fsa = unsafe {
+            fsa_from_arc_list(states, &*vec_t::new(&mut qfs), &*vec_t::new(&mut carcs))
+        };
+        Ok(Automaton {
+            fsa: sync::Arc::new(fsa),
+            labels: sync::Arc::new(integeriser),
+            weights: PhantomData,
+        })
+    }
+}
+
+impl<'de, T, W> Deserialize<'de> for Automaton<T, W>
+where
+    T: Deserialize<'de> + Hash + Eq + Clone,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Automaton<T, W>, D::Error> {
+        if deserializer.is_human_readable() {
+            return deserializer.deserialize_struct(
+                "Automaton",
+                READABLE_FIELDS,
+                ReadableAutomatonVisitor {
+                    automaton: PhantomData,
+                },
+            );
+        }
+
+        deserializer.deserialize_tuple(
+            3,
+            AutomatonVisitor {
+                automaton: PhantomData,
+            },
+        )
+    }
+}
+
+impl<T, W> Debug for Automaton<T, W>
+where
+    T: Debug + Hash + Eq,
+{
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(
+            f,
+            "Automaton {{ fsa: {:?}, labels: {:?} }}",
+            self.fsa,
+            self.labels
+        )
+    }
+}
+
+/// Two Automata are equal if they recognize the same weighted language, see
+/// `Automaton::equivalent`; their states and transitions may differ.
+/// Since weights are compared up to a small tolerance, this is not an equivalence
+/// relation in general and `Automaton` does not implement `Eq`.
+impl<T> PartialEq for Automaton<T>
+where
+    T: Hash + Eq + Clone,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.equivalent(other)
+    }
+}
+
+impl<T, W> Display for Automaton<T, W>
+where
+    T: Display + Hash + Eq + Clone,
+    W: Semiring + Display,
+{
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let (arcs, q0, qfs) = self.clone().into_arcs();
+
+        // the order of transitions in OpenFst is not stable, so they are sorted
+        let mut arcs: Vec<(usize, usize, String, String)> = arcs.iter()
+            .map(|arc| (arc.from, arc.to, format!("{}", arc.label), format!("{}", arc)))
+            .collect();
+        arcs.sort();
+
+        let qfs_strings: Vec<String> = qfs.iter().map(|q| format!("{}", q)).collect();
+        let arc_strings: Vec<String> = arcs.into_iter().map(|(_, _, _, arc)| arc).collect();
+
+        write!(
+            f,
+            "initial {}\nfinal: {}\n{}",
+            q0,
+            qfs_strings.join(", "),
+            arc_strings.join("\n")
+        )
+    }
+}
+
+impl<T, Q, W> Display for Arc<Q, T, W>
+where
+    T: Display,
+    Q: Display,
+    W: Display,
+{
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(
+            f,
+            "{}[{}]\t→ {} # {}",
+            self.from,
+            self.label,
+            self.to,
+            self.weight
+        )
+    }
+}
+
+
+
+// tests
+
+#[cfg(test)]
+mod tests {
+    use fsa::*;
+    use num_traits::One;
+    use rand::{SeedableRng, prng::XorShiftRng};
+    use std::thread;
+    use std::f32;
+
+    // a loop that reads "a" with weight 0.9 from q1 to q2 and "word" back to q1
+    pub(crate) fn loop_arcs() -> Vec<Arc<&'static str, &'static str>> {
+        vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ]
+    }
+
+    // the loop of `loop_arcs` with the final state q1, i.e. the language (a word)*
+    pub(crate) fn loop_automaton() -> Automaton<&'static str> {
+        Automaton::from_arcs("q1", vec!["q1"], loop_arcs())
+    }
+
+    #[test]
+    fn display() {
+        let arcs = vec![
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: Label::Symbol("word"),
+                weight: Tropical(1.0),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: Label::Symbol("a"),
+                weight: Tropical(0.5),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: Label::Epsilon,
+                weight: Tropical(2.0),
+            },
+        ];
+        let fsa = Automaton::from_arcs_with_epsilons("q1", vec!["q1"], arcs.clone());
+        let output = "initial 0\nfinal: 0\n0[a]\t→ 1 # 0.5\n0[ε]\t→ 1 # 2\n1[word]\t→ 0 # 1";
+        assert_eq!(output, format!("{}", fsa));
+
+        let arcs = arcs.into_iter().rev().collect();
+        let fsa = Automaton::from_arcs_with_epsilons("q1", vec!["q1"], arcs);
+        assert_eq!(output, format!("{}", fsa));
+    }
+
+    #[test]
+    fn simple_fsa() {
+        let arcs = vec![
+            Arc {
+                from: "q",
+                to: "q",
+                label: "word",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        let arcs_ = vec![
+            Arc {
+                from: 0,
+                to: 0,
+                label: Label::Symbol("word"),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q", vec!["q"], arcs);
+
+        assert_eq!((arcs_, 0, vec![0]), fsa.into_arcs());
+    }
+
+    #[test]
+    fn empty_word_acceptor() {
+        let fsa: Automaton<&str> = Automaton::from_arcs("q", vec!["q"], Vec::new());
+
+        assert_eq!(1, fsa.num_states());
+        assert_eq!((Vec::new(), 0, vec![0]), fsa.clone().into_arcs());
+
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = fsa.generate(2).flatten().collect();
+        assert_eq!(vec![(Vec::new(), LogDomain::one())], words);
+    }
+
+    #[test]
+    fn final_state_lookup() {
+        let fsa = loop_automaton();
+
+        assert!(fsa.is_final(0));
+        assert!(!fsa.is_final(1));
+        assert!(!fsa.is_final(2));
+        assert!(!fsa.is_final(usize::MAX));
+    }
+
+    #[test]
+    fn reweighting() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs("q1", vec!["q1"], arcs);
+        let squared = fsa.reweight(&fsa).unwrap();
+
+        let (arcs, q0, qfs) = fsa.clone().into_arcs();
+        let (arcs_, q0_, qfs_) = squared.into_arcs();
+        assert_eq!((q0, qfs), (q0_, qfs_));
+        for (arc, arc_) in arcs.into_iter().zip(arcs_) {
+            assert_eq!((arc.from, arc.to, arc.label), (arc_.from, arc_.to, arc_.label));
+            assert!((arc.weight.pow(2.0).value() - arc_.weight.value()).abs() < 1e-6);
+        }
+
+        let other = Automaton::from_arcs(
+            "q1",
+            vec!["q2"],
+            vec![Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            }],
+        );
+        assert_eq!(Err(FsaError::DifferentStructure), fsa.reweight(&other).map(|_| ()));
+    }
+
+    #[test]
+    fn simple_intersection() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs.clone());
+        let fsa_ = Automaton::from_arcs("q1", vec!["q1"], arcs);
+
+        let arcs_ = vec![
+            Arc {
+                from: 0,
+                to: 1,
+                label: Label::Symbol("a"),
+                weight: LogDomain::new(0.9).unwrap().pow(2.0),
+            },
+            Arc {
+                from: 1,
+                to: 0,
+                label: Label::Symbol("word"),
+                weight: LogDomain::one(),
+            },
+        ];
+
+        let intersection = fsa.intersect(&fsa_).unwrap();
+
+        assert_eq!((arcs_, 0, vec![0]), intersection.into_arcs());
+    }
+
+    #[test]
+    fn num_states() {
+        let arcs = vec![
+            Arc {
+                from: "q",
+                to: "q",
+                label: "word",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        assert_eq!(1, Automaton::from_arcs("q", vec!["q"], arcs).num_states());
+
+        let fsa = loop_automaton();
+        assert_eq!(2, fsa.intersect(&fsa).unwrap().num_states());
+    }
+
+    #[test]
+    fn num_arcs() {
+        let fsa = loop_automaton();
+        let intersection = fsa.intersect(&fsa).unwrap();
+
+        assert_eq!(intersection.clone().into_arcs().0.len(), intersection.num_arcs());
+    }
+
+    #[test]
+    fn arc_iterator_construction() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q2",
+                to: "q2",
+                label: "b",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs[0..2].to_vec());
+        let fsa_ = Automaton::from_arc_iter(
+            "q1",
+            vec!["q1"],
+            arcs.into_iter().filter(|arc| arc.from != arc.to),
+        );
+
+        assert_eq!(fsa.into_arcs(), fsa_.into_arcs());
+    }
+
+    #[test]
+    fn initial_and_final_states() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q3", "q1"], arcs);
+
+        assert_eq!(0, fsa.initial_state());
+        assert_eq!(vec![0, 1], fsa.final_states());
+
+        let (_, q0, qfs) = fsa.into_arcs();
+        assert_eq!((0, vec![0, 1]), (q0, qfs));
+    }
+
+    #[test]
+    fn final_state_weights() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q1", "q2"], arcs);
+
+        assert_eq!(
+            vec![(0, LogDomain::one()), (1, LogDomain::one())],
+            fsa.final_weights()
+        );
+
+        // pushing moves the weight of the transition to the final state
+        let pushed = fsa.push_weights(PushDirection::ToFinal).final_weights();
+        assert_eq!(2, pushed.len());
+        assert!((pushed[1].1.value() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn weighted_final_states() {
+        let arcs = loop_arcs();
+        let qf = LogDomain::new(0.5).unwrap();
+        let fsa = Automaton::from_arcs_weighted("q1", vec![("q2", qf)], arcs);
+
+        assert_eq!(vec![(1, qf)], fsa.final_weights());
+
+        let weight = fsa.weight_of(&["a"]).unwrap();
+        assert!((weight.value() - 0.45).abs() < 1e-6);
+
+        let (word, weight_) = fsa.best_path().unwrap();
+        assert_eq!(vec!["a"], word);
+        assert!((weight_.value() - 0.45).abs() < 1e-6);
+
+        let mut att = Vec::new();
+        fsa.write_att(&mut att).unwrap();
+        let fsa_ = Automaton::<&str>::read_att(sync::Arc::clone(&fsa.labels), att.as_slice())
+            .unwrap();
+        assert_eq!(fsa.final_weights(), fsa_.final_weights());
+    }
+
+    #[test]
+    fn arcs_with_final_weights() {
+        let arcs = loop_arcs();
+        let qf = LogDomain::new(0.5).unwrap();
+        let fsa = Automaton::from_arcs_weighted("q1", vec![("q1", LogDomain::one()), ("q2", qf)], arcs);
+
+        let (arcs_, q0, finals) = fsa.clone().into_arcs_weighted();
+        assert_eq!(vec![(0, LogDomain::one()), (1, qf)], finals);
+
+        let fsa_ = Automaton::from_arcs_with_epsilons_weighted(q0, finals, arcs_);
+        assert_eq!(fsa.final_weights(), fsa_.final_weights());
+        assert_eq!(fsa.clone().into_arcs(), fsa_.clone().into_arcs());
+        assert!(
+            (fsa_.weight_of(&["a"]).unwrap().value() - 0.45).abs() < 1e-6
+        );
+
+        // the weights of final states are lost by `into_arcs`
+        let (arcs_, q0, finals) = fsa.into_arcs();
+        let unweighted = Automaton::from_arcs_with_epsilons(q0, finals, arcs_);
+        assert!(
+            (unweighted.weight_of(&["a"]).unwrap().value() - 0.9).abs() < 1e-6
+        );
+    }
+
+    #[test]
+    fn intersection_of_independent_labels() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "b",
+                weight: LogDomain::one(),
+            },
+        ];
+        let arcs_ = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "c",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q3",
+                to: "q4",
+                label: "b",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q3"], arcs);
+        let fsa_ = Automaton::from_arcs("q1", vec!["q2", "q4"], arcs_);
+
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = fsa.intersect(&fsa_)
+            .unwrap()
+            .generate(2)
+            .flatten()
+            .collect();
+        assert_eq!(1, words.len());
+        assert_eq!(vec!["a", "b"], words[0].0);
+        assert!((words[0].1.value() - 0.45).abs() < 1e-6);
+
+        let words: Vec<Vec<&str>> = fsa_.intersect(&fsa)
+            .unwrap()
+            .generate(2)
+            .flatten()
+            .map(|(word, _)| word)
+            .collect();
+        assert_eq!(vec![vec!["a", "b"]], words);
+    }
+
+    #[test]
+    fn difference_of_independent_labels() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "b",
+                weight: LogDomain::new(0.1).unwrap(),
+            },
+        ];
+        let arcs_ = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "c",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "b",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs);
+        let fsa_ = Automaton::from_arcs("q1", vec!["q2"], arcs_);
+
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = fsa.difference(&fsa_)
+            .unwrap()
+            .generate(2)
+            .flatten()
+            .collect();
+        assert_eq!(vec![(vec!["a"], LogDomain::new(0.9).unwrap())], words);
+
+        let words: Vec<Vec<&str>> = fsa_.difference(&fsa)
+            .unwrap()
+            .generate(2)
+            .flatten()
+            .map(|(word, _)| word)
+            .collect();
+        assert_eq!(vec![vec!["c"]], words);
+    }
+
+    #[test]
+    fn relabeling() {
+        let arcs = loop_arcs();
+        let arcs_ = vec![
+            Arc {
+                from: "q1",
+                to: "q1",
+                label: "b",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs);
+        let fsa_ = Automaton::from_arcs("q1", vec!["q1"], arcs_).relabel(&fsa.labels);
+
+        assert_eq!(fsa.labels.find_key(&"a"), fsa_.labels.find_key(&"a"));
+        assert_eq!(fsa.labels.find_key(&"word"), fsa_.labels.find_key(&"word"));
+        assert_eq!(Some(2), fsa_.labels.find_key(&"b"));
+
+        let ww = LogDomain::new(0.9).unwrap();
+        let words = vec![(Vec::new(), LogDomain::one()), (vec!["a", "word"], ww)];
+        assert_eq!(words, fsa.intersect(&fsa_).unwrap().n_best(2));
+    }
+
+    #[test]
+    fn simple_union() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        let arcs_ = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "word",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+        ];
+        // both Automata use the label id 0 for different labels
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs);
+        let fsa_ = Automaton::from_arcs("q1", vec!["q2"], arcs_);
+
+        let mut language: Vec<(Vec<&str>, LogDomain<f32>)> = fsa.union(&fsa_)
+            .generate(2)
+            .flatten()
+            .collect();
+        language.sort();
+
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (vec!["a"], LogDomain::new(0.9).unwrap()),
+            (vec!["word"], LogDomain::new(0.5).unwrap()),
+        ];
+
+        assert_eq!(words, language);
+    }
+
+    #[test]
+    fn simple_concatenation() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        let arcs_ = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "word",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs);
+        let fsa_ = Automaton::from_arcs("q1", vec!["q2"], arcs_);
+
+        let concat_arcs = vec![
+            Arc {
+                from: 0,
+                to: 1,
+                label: Label::Symbol("a"),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: 1,
+                to: 2,
+                label: Label::Symbol("word"),
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+        ];
+
+        assert_eq!((concat_arcs, 0, vec![2]), fsa.concat(&fsa_).into_arcs());
+    }
+
+    #[test]
+    fn concatenation_with_epsilon_and_empty_language() {
+        let arcs = loop_arcs();
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs.clone());
+        let fsa_ = Automaton::from_arcs("q1", vec!["q2"], arcs.clone());
+        let empty = Automaton::from_arcs("q1", Vec::new(), arcs);
+
+        // fsa accepts the empty word, so every word of fsa_ is in the concatenation
+        let language: Vec<(Vec<&str>, LogDomain<f32>)> =
+            fsa.concat(&fsa_).generate(2).flatten().take(2).collect();
+        let ww = LogDomain::new(0.9).unwrap();
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (vec!["a"], ww),
+            (vec!["a", "word", "a"], ww.pow(2.0)),
+        ];
+        assert_eq!(words, language);
+
+        assert!(fsa.concat(&empty).generate(2).next().is_none());
+        assert!(empty.concat(&fsa).generate(2).next().is_none());
+    }
+
+    #[test]
+    fn closure_generator() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs);
+        let w = LogDomain::new(0.9).unwrap();
+
+        let star: Vec<(Vec<&str>, LogDomain<f32>)> = fsa.closure(ClosureKind::Star)
+            .generate(3)
+            .flatten()
+            .take(4)
+            .collect();
+        let star_words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (Vec::new(), LogDomain::one()),
+            (vec!["a"], w),
+            (vec!["a", "a"], w.pow(2.0)),
+            (vec!["a", "a", "a"], w.pow(3.0)),
+        ];
+        assert_eq!(star_words, star);
+
+        let plus: Vec<(Vec<&str>, LogDomain<f32>)> = fsa.closure(ClosureKind::Plus)
+            .generate(3)
+            .flatten()
+            .take(3)
+            .collect();
+        let plus_words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (vec!["a"], w),
+            (vec!["a", "a"], w.pow(2.0)),
+            (vec!["a", "a", "a"], w.pow(3.0)),
+        ];
+        assert_eq!(plus_words, plus);
+    }
+
+    #[test]
+    fn reversed_language() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let language: Vec<(Vec<&str>, LogDomain<f32>)> =
+            Automaton::from_arcs("q1", vec!["q3"], arcs)
+                .reverse()
+                .generate(2)
+                .flatten()
+                .collect();
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> =
+            vec![(vec!["word", "a"], LogDomain::new(0.9).unwrap())];
+
+        assert_eq!(words, language);
+    }
+
+    #[test]
+    fn determinization() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.3).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::new(0.2).unwrap(),
+            },
+        ];
+        let (arcs_, q0, qfs) = Automaton::from_arcs("q1", vec!["q2", "q3"], arcs)
+            .determinize()
+            .into_arcs();
+
+        assert_eq!((0, vec![1]), (q0, qfs));
+        assert_eq!(1, arcs_.len());
+        assert_eq!((0, 1, Label::Symbol("a")), (arcs_[0].from, arcs_[0].to, arcs_[0].label.clone()));
+        assert!((arcs_[0].weight.value() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn minimization() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "b",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q2",
+                to: "q4",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q3",
+                to: "q4",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q4"], arcs);
+        let min = fsa.minimize();
+        assert_eq!((4, 3), (fsa.num_states(), min.num_states()));
+
+        let mut language: Vec<(Vec<&str>, LogDomain<f32>)> =
+            min.generate(2).flatten().collect();
+        language.sort();
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (vec!["a", "word"], LogDomain::one()),
+            (vec!["b", "word"], LogDomain::one()),
+        ];
+        assert_eq!(words, language);
+    }
+
+    #[test]
+    fn epsilon_removal() {
+        let arcs = vec![
+            Arc {
+                from: "q0",
+                to: "q1",
+                label: Label::Epsilon,
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: Label::Symbol("a"),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        let (arcs_, q0, qfs) = Automaton::from_arcs_with_epsilons("q0", vec!["q2"], arcs)
+            .remove_epsilon()
+            .into_arcs();
+
+        assert_eq!(1, arcs_.len());
+        assert_eq!((q0, Label::Symbol("a")), (arcs_[0].from, arcs_[0].label.clone()));
+        assert_eq!(vec![arcs_[0].to], qfs);
+        assert!((arcs_[0].weight.value() - 0.45).abs() < 1e-6);
+    }
+
+    #[test]
+    fn epsilon_transitions() {
+        let arcs = vec![
+            Arc {
+                from: "q0",
+                to: "q1",
+                label: Label::Epsilon,
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: Label::Symbol("a"),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs_with_epsilons("q0", vec!["q2"], arcs.clone());
+
+        let arcs_ = arcs.into_iter()
+            .zip(vec![(0, 1), (1, 2)])
+            .map(|(arc, (from, to))| Arc {
+                from,
+                to,
+                label: arc.label,
+                weight: arc.weight,
+            })
+            .collect();
+        assert_eq!((arcs_, 0, vec![2]), fsa.clone().into_arcs());
+        assert_eq!(Some(Label::Epsilon), fsa.arcs_from(0).map(|arc| arc.label).next());
+        assert_eq!(2, fsa.sorted_arcs_by_weight().len());
+
+        let (arcs_, q0, qfs) = fsa.clone().into_arcs_weighted();
+        let fsa_ = Automaton::from_arcs_with_epsilons_weighted(q0, qfs, arcs_);
+        assert_eq!(fsa.clone().into_arcs(), fsa_.into_arcs());
+
+        let language: Vec<(Vec<&str>, LogDomain<f32>)> =
+            fsa.clone().generate(2).flatten().collect();
+        assert_eq!(vec![(vec!["a"], LogDomain::new(0.9).unwrap())], language);
+        assert_eq!(Some((vec!["a"], LogDomain::new(0.9).unwrap())), fsa.best_path());
+    }
+
+    #[test]
+    fn arc_sorting() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "a",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q3",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs);
+        let mut arcs_ = fsa.clone().into_arcs().0;
+        arcs_.sort();
+
+        for &by in &[SortKey::State, SortKey::ILabel] {
+            let mut sorted = fsa.arc_sort(by).into_arcs().0;
+            sorted.sort();
+            assert_eq!(arcs_, sorted);
+        }
+
+        let intersection = fsa.arc_sort(SortKey::ILabel).intersect(&fsa).unwrap();
+        assert_eq!(
+            fsa.intersect(&fsa).unwrap().n_best(3),
+            intersection.n_best(3)
+        );
+    }
+
+    #[test]
+    fn sequence_intersection() {
+        // all words over a and b, where b is less likely
+        let weighted: Automaton<&str> = Automaton::from_arcs(
+            0,
+            vec![0],
+            vec![
+                Arc {
+                    from: 0,
+                    to: 0,
+                    label: "a",
+                    weight: LogDomain::new(0.5).unwrap(),
+                },
+                Arc {
+                    from: 0,
+                    to: 0,
+                    label: "b",
+                    weight: LogDomain::new(0.25).unwrap(),
+                },
+            ],
+        );
+        // words of even length
+        let even: Automaton<&str> = Automaton::from_arcs(
+            0,
+            vec![0],
+            vec!["a", "b"]
+                .into_iter()
+                .flat_map(|label| {
+                    vec![
+                        Arc {
+                            from: 0,
+                            to: 1,
+                            label,
+                            weight: LogDomain::one(),
+                        },
+                        Arc {
+                            from: 1,
+                            to: 0,
+                            label,
+                            weight: LogDomain::one(),
+                        },
+                    ]
+                })
+                .collect(),
+        );
+        // words that start with b
+        let start: Automaton<&str> = Automaton::from_arcs(
+            0,
+            vec![1],
+            vec![
+                Arc {
+                    from: 0,
+                    to: 1,
+                    label: "b",
+                    weight: LogDomain::one(),
+                },
+                Arc {
+                    from: 1,
+                    to: 1,
+                    label: "a",
+                    weight: LogDomain::one(),
+                },
+                Arc {
+                    from: 1,
+                    to: 1,
+                    label: "b",
+                    weight: LogDomain::one(),
+                },
+            ],
+        );
+
+        let product = weighted.clone()
+            .intersect_all(vec![even.clone(), start.clone()])
+            .unwrap();
+        let product_ = start.clone()
+            .intersect_all(vec![weighted.clone(), even.clone()])
+            .unwrap();
+        assert_eq!(product, product_);
+        assert_eq!(product, weighted.intersect(&even).unwrap().intersect(&start).unwrap());
+        assert_eq!(
+            Some((vec!["b", "a"], LogDomain::new(0.125).unwrap())),
+            product.best_path()
+        );
+    }
+
+    #[test]
+    fn sorted_intersection() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "word",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "a",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q3",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs("q1", vec!["q1"], arcs);
+        let by_state = fsa.arc_sort(SortKey::State);
+
+        let intersection = fsa.intersect(&fsa).unwrap();
+        let sorted = by_state.intersect_sorted(&by_state).unwrap();
+        assert_eq!(intersection.clone().into_arcs(), sorted.clone().into_arcs());
+        assert!(intersection.equivalent(&sorted));
+    }
+
+    #[test]
+    fn topological_sorting() {
+        let arcs = vec![
+            Arc {
+                from: "q3",
+                to: "q4",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::new(0.1).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q4"], arcs);
+        let sorted = fsa.topsort().unwrap();
+
+        assert_eq!(fsa.n_best(3), sorted.n_best(3));
+        for arc in sorted.into_arcs().0 {
+            assert!(arc.from < arc.to);
+        }
+
+        let cycle = loop_arcs();
+        assert!(Automaton::from_arcs("q1", vec!["q1"], cycle).topsort().is_none());
+    }
+
+    #[test]
+    fn pruning() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "b",
+                weight: LogDomain::new(0.01).unwrap(),
+            },
+            Arc {
+                from: "q3",
+                to: "q2",
+                label: "c",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "c",
+                weight: LogDomain::new(0.05).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs);
+
+        let pruned = fsa.prune(LogDomain::new(0.1).unwrap());
+        let labels: Vec<Label<&str>> = pruned
+            .clone()
+            .into_arcs()
+            .0
+            .into_iter()
+            .map(|arc| arc.label)
+            .collect();
+        assert_eq!(vec![Label::Symbol("a")], labels);
+        assert_eq!(fsa.n_best(1), pruned.n_best(3));
+
+        assert_eq!(2, fsa.prune(LogDomain::new(0.01).unwrap()).n_best(3).len());
+        assert_eq!(3, fsa.prune(LogDomain::new(0.001).unwrap()).n_best(3).len());
+    }
+
+    #[test]
+    fn sequence_concatenation() {
+        let parts = vec!["a", "b", "c"].into_iter().map(|label| {
+            Automaton::from_arcs(
+                0,
+                vec![1],
+                vec![
+                    Arc {
+                        from: 0,
+                        to: 1,
+                        label,
+                        weight: LogDomain::new(0.5).unwrap(),
+                    },
+                ],
+            )
+        });
+
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> =
+            Automaton::concat_all(parts).generate(2).flatten().collect();
+        assert_eq!(1, words.len());
+        assert_eq!(vec!["a", "b", "c"], words[0].0);
+        assert!((words[0].1.value() - 0.125).abs() < 1e-6);
+
+        let empty: Vec<Automaton<&str>> = Vec::new();
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> =
+            Automaton::concat_all(empty).generate(2).flatten().collect();
+        assert_eq!(vec![(Vec::new(), LogDomain::one())], words);
+    }
+
+    #[test]
+    fn sequence_union() {
+        let words = vec![vec!["a", "b"], vec!["b"], vec!["c", "a", "b"]];
+        let parts = words.iter().map(|word| {
+            let arcs = word.iter()
+                .enumerate()
+                .map(|(i, &label)| Arc {
+                    from: i,
+                    to: i + 1,
+                    label,
+                    weight: LogDomain::one(),
+                })
+                .collect();
+            Automaton::from_arcs(0, vec![word.len()], arcs)
+        });
+
+        let mut words_: Vec<Vec<&str>> = Automaton::union_all(parts)
+            .generate(2)
+            .flatten()
+            .map(|(word, _)| word)
+            .collect();
+        words_.sort();
+        let mut words = words;
+        words.sort();
+        assert_eq!(words, words_);
+
+        let empty: Vec<Automaton<&str>> = Vec::new();
+        assert_eq!(None, Automaton::union_all(empty).best_path());
+    }
+
+    #[test]
+    fn arcs_of_a_state() {
+        let fsa = loop_automaton();
+
+        for state in 0..2 {
+            let arcs_: Vec<Arc<usize, Label<&str>>> =
+                fsa.arcs().filter(|arc| arc.from == state).collect();
+            assert_eq!(arcs_, fsa.arcs_from(state).collect::<Vec<_>>());
+        }
+        assert_eq!(1, fsa.arcs_from(0).count());
+        assert_eq!(0, fsa.arcs_from(2).count());
+        assert_eq!(0, fsa.arcs_from(usize::MAX).count());
+    }
+
+    #[test]
+    fn weight_mapping() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+        ];
+        let final_states = vec![("q1", LogDomain::new(0.2).unwrap())];
+        let fsa = Automaton::from_arcs_weighted("q1", final_states, arcs);
+        let squared = fsa.map_weights(|weight| weight.pow(2.0));
+
+        let (arcs, q0, qfs) = fsa.clone().into_arcs();
+        let (arcs_, q0_, qfs_) = squared.clone().into_arcs();
+        assert_eq!((q0, qfs), (q0_, qfs_));
+        for (arc, arc_) in arcs.into_iter().zip(arcs_) {
+            assert_eq!((arc.from, arc.to, arc.label), (arc_.from, arc_.to, arc_.label));
+            assert!((arc.weight.value().powi(2) - arc_.weight.value()).abs() < 1e-6);
+        }
+        assert!((squared.final_weights()[0].1.value() - 0.04).abs() < 1e-6);
+    }
+
+    #[test]
+    fn word_acceptor() {
+        let fsa = loop_automaton();
+        let word: Automaton<&str> = Automaton::from_word(&["a", "word"]);
+
+        assert_eq!(3, word.num_states());
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = fsa.intersect(&word)
+            .unwrap()
+            .generate(2)
+            .flatten()
+            .collect();
+        assert_eq!(vec![(vec!["a", "word"], LogDomain::new(0.9).unwrap())], words);
+
+        let empty_word: Automaton<&str> = Automaton::from_word(&[]);
+        assert_eq!(Some((Vec::new(), LogDomain::one())), empty_word.best_path());
+    }
+
+    #[test]
+    fn trie_acceptor() {
+        let words = vec![
+            (vec!["a", "word"], LogDomain::new(0.5).unwrap()),
+            (vec!["a"], LogDomain::new(0.3).unwrap()),
+            (vec!["a", "b"], LogDomain::new(0.2).unwrap()),
+        ];
+        let fsa = Automaton::from_words(words.clone());
+
+        // the prefix "a" is shared by all words
+        assert_eq!(4, fsa.num_states());
+        assert_eq!(words, fsa.n_best(3));
+    }
+
+    #[test]
+    fn arc_filtering() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "b",
+                weight: LogDomain::new(0.1).unwrap(),
+            },
+            Arc {
+                from: "q3",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs);
+
+        let retained = fsa.retain_arcs(|arc| arc.label != "b");
+        assert_eq!(2, retained.num_states());
+        assert_eq!(vec![(vec!["a"], LogDomain::new(0.9).unwrap())], retained.n_best(3));
+
+        let all = fsa.retain_arcs(|_| true);
+        assert_eq!(fsa.n_best(3), all.n_best(3));
+    }
+
+    #[test]
+    fn label_mapping() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q3"], arcs);
+
+        let words: Vec<(Vec<String>, LogDomain<f32>)> = fsa.map_labels(|_| "x".to_string())
+            .generate(2)
+            .flatten()
+            .collect();
+        assert_eq!(
+            vec![(vec!["x".to_string(), "x".to_string()], LogDomain::new(0.9).unwrap())],
+            words
+        );
+
+        let upper = fsa.map_labels(|label| label.to_uppercase());
+        assert_eq!(vec!["A", "WORD"], upper.best_path().unwrap().0);
+    }
+
+    #[test]
+    fn arcs_sorted_by_weight() {
+        let arcs = vec![
+            Arc {
+                from: 0,
+                to: 1,
+                label: "a",
+                weight: LogDomain::new(0.2).unwrap(),
+            },
+            Arc {
+                from: 0,
+                to: 1,
+                label: "b",
+                weight: LogDomain::new(0.8).unwrap(),
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs(0, vec![1], arcs);
+
+        let labels: Vec<Label<&str>> = fsa
+            .sorted_arcs_by_weight()
+            .into_iter()
+            .map(|arc| arc.label)
+            .collect();
+        assert_eq!(vec![Label::Symbol("b"), Label::Symbol("a")], labels);
+
+        let weights: Vec<f32> = fsa
+            .sorted_arcs_by_weight()
+            .into_iter()
+            .map(|arc| arc.weight.value())
+            .collect();
+        assert!(weights[0] > weights[1]);
+    }
+
+    #[test]
+    fn versioned_serialization() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a".to_string(),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word".to_string(),
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa: Automaton<String> = Automaton::from_arcs("q1", vec!["q1"], arcs);
+
+        let mut bytes = bincode::serialize(&fsa).unwrap();
+        let deserialized: Automaton<String> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(fsa.clone().into_arcs(), deserialized.into_arcs());
+
+        // the version is stored in the first bytes
+        bytes[0] += 1;
+        let error = bincode::deserialize::<Automaton<String>>(&bytes).unwrap_err();
+        assert!(error.to_string().contains("unsupported version 2"));
+    }
+
+    #[test]
+    fn readable_serialization() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a".to_string(),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word".to_string(),
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa: Automaton<String> = Automaton::from_arcs("q1", vec!["q1"], arcs);
+
+        let json = serde_json::to_string(&fsa).unwrap();
+        assert!(json.contains("\"labels\":[\"a\",\"word\"]"));
+        let deserialized: Automaton<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(fsa.clone().into_arcs(), deserialized.into_arcs());
+
+        let bytes = bincode::serialize(&fsa).unwrap();
+        let deserialized: Automaton<String> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(fsa.clone().into_arcs(), deserialized.into_arcs());
+
+        let unversioned = json.replace("\"version\":1,", "");
+        assert_ne!(json, unversioned);
+        let error = serde_json::from_str::<Automaton<String>>(&unversioned).err().unwrap();
+        assert!(error.to_string().contains("missing field `version`"));
+
+        let json = json.replace("[1,0,2,", "[1,0,3,");
+        assert!(serde_json::from_str::<Automaton<String>>(&json).is_err());
+    }
+
+    #[test]
+    fn structural_hashing() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "b",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs("q1", vec!["q2", "q3"], arcs.clone());
+        let reversed: Automaton<&str> =
+            Automaton::from_arcs("q1", vec!["q2", "q3"], arcs.iter().cloned().rev().collect());
+        assert_eq!(fsa.structural_hash(), reversed.structural_hash());
+
+        let fewer: Automaton<&str> = Automaton::from_arcs("q1", vec!["q2", "q3"], arcs[1..].to_vec());
+        assert_ne!(fsa.structural_hash(), fewer.structural_hash());
+    }
+
+    #[test]
+    fn huge_costs() {
+        let tiny = LogDomain::new(f32::consts::E).unwrap().pow(-1e4);
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: tiny,
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs("q1", vec!["q2"], arcs);
+
+        let (arcs, _, _) = fsa.clone().into_arcs();
+        assert_eq!(tiny, arcs[0].weight);
+        assert!(arcs[0].weight > LogDomain::zero());
+        assert_eq!(Some(tiny), fsa.weight_of(&["a"]));
+    }
+
+    #[test]
+    fn shared_integeriser() {
+        let mut integeriser = HashIntegeriser::new();
+        integeriser.integerise("a");
+        integeriser.integerise("b");
+        let integeriser = sync::Arc::new(integeriser);
+
+        let fsa: Automaton<&str> = Automaton::from_arcs_with_integeriser(
+            sync::Arc::clone(&integeriser),
+            0,
+            vec![0],
+            vec![
+                Arc {
+                    from: 0,
+                    to: 0,
+                    label: "b",
+                    weight: LogDomain::new(0.5).unwrap(),
+                },
+            ],
+        );
+        let fsa_: Automaton<&str> = Automaton::from_arcs_with_integeriser(
+            sync::Arc::clone(&integeriser),
+            0,
+            vec![1],
+            vec![
+                Arc {
+                    from: 0,
+                    to: 1,
+                    label: "b",
+                    weight: LogDomain::one(),
+                },
+            ],
+        );
+        assert!(sync::Arc::ptr_eq(&integeriser, &fsa.labels));
+        assert!(sync::Arc::ptr_eq(&integeriser, &fsa_.labels));
+
+        let intersection = fsa.intersect(&fsa_).unwrap();
+        assert!(sync::Arc::ptr_eq(&integeriser, &intersection.labels));
+        assert_eq!(vec![(vec!["b"], LogDomain::new(0.5).unwrap())], intersection.n_best(2));
+
+        let extended: Automaton<&str> = Automaton::from_arcs_with_integeriser(
+            sync::Arc::clone(&integeriser),
+            0,
+            vec![1],
+            vec![
+                Arc {
+                    from: 0,
+                    to: 1,
+                    label: "c",
+                    weight: LogDomain::one(),
+                },
+            ],
+        );
+        assert_eq!(Some(2), extended.symbols().find_key(&"c"));
+        assert_eq!(2, integeriser.size());
+    }
+
+    #[test]
+    fn symbol_table() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "word",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "a",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs("q1", vec!["q1"], arcs);
+
+        let (raw_arcs, _, _) = fsa.clone().into_raw_arcs();
+        let (arcs, _, _) = fsa.clone().into_arcs();
+        for (&(_, _, label_id, _), arc) in raw_arcs.iter().zip(&arcs) {
+            assert_eq!(Label::Symbol(*fsa.symbols().find_value(label_id - 1).unwrap()), arc.label);
+        }
+        assert_eq!(Some(&"a"), fsa.symbols().find_value(fsa.symbols().find_key(&"a").unwrap()));
+        assert_eq!(2, fsa.symbols().size());
+    }
+
+    #[test]
+    fn raw_arcs() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs);
+
+        let (arcs, q0, qfs) = fsa.clone().into_arcs();
+        let (raw_arcs, q0_, qfs_) = fsa.clone().into_raw_arcs();
+        assert_eq!((q0, qfs), (q0_, qfs_));
+        assert_eq!(arcs.len(), raw_arcs.len());
+        for (arc, (from, to, label, weight)) in arcs.into_iter().zip(raw_arcs) {
+            assert_eq!((arc.from, arc.to), (from, to));
+            assert_eq!(Label::Symbol(*fsa.labels.find_value(label - 1).unwrap()), arc.label);
+            assert!((-arc.weight.ln() - weight).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn connection() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "b",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q3",
+                to: "q4",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let arcs_ = vec![
+            Arc {
+                from: 0,
+                to: 1,
+                label: Label::Symbol("a"),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs).connect();
+
+        assert_eq!((arcs_, 0, vec![1]), fsa.into_arcs());
+    }
+
+    #[test]
+    fn unreachable_final_states() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q3",
+                to: "q2",
+                label: "b",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs("q1", vec!["q2"], arcs.clone());
+        assert_eq!(Ok(()), fsa.validate());
+
+        let fsa: Automaton<&str> = Automaton::from_arcs("q1", vec!["q2", "q3", "q4"], arcs);
+        assert_eq!(Err(FsaError::UnreachableFinal(2)), fsa.validate());
+        assert_eq!(Ok(()), fsa.connect().validate());
+    }
+
+    #[test]
+    fn states_on_cycles() {
+        let fsa = loop_automaton();
+        assert!(fsa.state_on_cycle(0));
+        assert!(fsa.state_on_cycle(1));
+        assert!(!fsa.state_on_cycle(2));
+
+        let chain: Automaton<&str> = Automaton::from_word(&["a", "b", "c"]);
+        assert!((0..chain.num_states()).all(|state| !chain.state_on_cycle(state)));
+    }
+
+    #[test]
+    fn emptiness() {
+        let arcs = loop_arcs();
+
+        assert!(Automaton::from_arcs("q1", Vec::new(), arcs.clone()).is_empty());
+        // the final state q3 is not reachable from q1
+        assert!(Automaton::from_arcs("q1", vec!["q3"], arcs.clone()).is_empty());
+        assert!(!Automaton::from_arcs("q1", vec!["q1"], arcs).is_empty());
+    }
+
+    #[test]
+    fn membership() {
+        let fsa = loop_automaton();
+
+        assert_eq!(Some(LogDomain::new(0.9).unwrap()), fsa.weight_of(&["a", "word"]));
+        assert_eq!(Some(LogDomain::one()), fsa.weight_of(&[]));
+        assert_eq!(None, fsa.weight_of(&["a"]));
+        assert_eq!(None, fsa.weight_of(&["b"]));
+    }
+
+    #[test]
+    fn compiled_membership() {
+        let fsa = loop_automaton();
+
+        let word = fsa.compile_query(&["a", "word", "a", "word"]).unwrap();
+        let weight = fsa.weight_of_compiled(&word);
+        assert_eq!(fsa.weight_of(&["a", "word", "a", "word"]), weight);
+        assert_eq!(weight, fsa.weight_of_compiled(&word));
+
+        assert_eq!(None, fsa.compile_query(&["b"]));
+        let word = fsa.compile_query(&["word"]).unwrap();
+        assert_eq!(None, fsa.weight_of_compiled(&word));
+
+        // words compiled with other labels are never queried
+        let fsa_ = Automaton::from_word(&["word", "a"]);
+        let word = fsa_.compile_query(&["a", "word"]).unwrap();
+        assert_eq!(None, fsa.weight_of_compiled(&word));
+        let word = fsa.compile_query(&["a", "word"]).unwrap();
+        assert!(fsa.clone().weight_of_compiled(&word).is_some());
+    }
+
+    #[test]
+    fn batched_membership() {
+        let fsa = loop_automaton();
+
+        let words = vec![
+            vec!["a", "word", "a", "word"],
+            vec!["a"],
+            vec!["b", "word"],
+            vec![],
+        ];
+        let weights: Vec<Option<LogDomain<f32>>> =
+            words.iter().map(|word| fsa.weight_of(word)).collect();
+        assert_eq!(weights, fsa.weights_of_batch(&words));
+        assert!(weights[0].is_some() && weights[3].is_some());
+        assert_eq!((None, None), (weights[1], weights[2]));
+        assert!(fsa.weights_of_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn equivalence() {
+        let ww = LogDomain::new(0.9).unwrap();
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: ww,
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let unrolled = vec![
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: ww,
+            },
+            Arc {
+                from: "q3",
+                to: "q4",
+                label: "a",
+                weight: ww,
+            },
+            Arc {
+                from: "q4",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let mut reweighted = arcs.clone();
+        reweighted[0].weight = LogDomain::new(0.8).unwrap();
+
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs);
+        let fsa_ = Automaton::from_arcs("q1", vec!["q1", "q3"], unrolled);
+        let fsa__ = Automaton::from_arcs("q1", vec!["q1"], reweighted);
+
+        assert!(fsa.equivalent(&fsa_));
+        assert!(fsa_.equivalent(&fsa));
+        assert!(!fsa.equivalent(&fsa__));
+    }
+
+    #[test]
+    fn language_equality() {
+        let fsa = loop_automaton();
+
+        let universal_arcs = vec!["a", "word"]
+            .into_iter()
+            .map(|label| Arc {
+                from: 0,
+                to: 0,
+                label,
+                weight: LogDomain::one(),
+            })
+            .collect();
+        let universal = Automaton::from_arcs(0, vec![0], universal_arcs);
+
+        assert_eq!(fsa, fsa.intersect(&universal).unwrap());
+        assert!(fsa != universal);
+    }
+
+    #[test]
+    fn path_sampling() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.75).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "b",
+                weight: LogDomain::new(0.25).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs.clone());
+        let mut rng = XorShiftRng::from_seed([42; 16]);
+
+        let samples = 10000;
+        let mut count = 0;
+        for _ in 0..samples {
+            let (word, weight) = fsa.sample_path(&mut rng).unwrap();
+            if word == vec!["a"] {
+                assert!((weight.value() - 0.75).abs() < 1e-6);
+                count += 1;
+            } else {
+                assert_eq!(vec!["b"], word);
+            }
+        }
+        assert!((count as f32 / samples as f32 - 0.75).abs() < 0.03);
+
+        let empty = Automaton::from_arcs("q1", Vec::new(), arcs);
+        assert_eq!(None, empty.sample_path(&mut rng));
+    }
+
+    #[test]
+    fn acyclicity() {
+        let arcs = loop_arcs();
+        let chain = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let self_loop = vec![
+            Arc {
+                from: "q",
+                to: "q",
+                label: "word",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+
+        assert!(!Automaton::from_arcs("q1", vec!["q1"], arcs).is_acyclic());
+        assert!(Automaton::from_arcs("q1", vec!["q3"], chain).is_acyclic());
+        assert!(!Automaton::from_arcs("q", vec!["q"], self_loop).is_acyclic());
+    }
+
+    #[test]
+    fn property_bits() {
+        let fsa = loop_automaton();
+
+        assert_eq!(
+            Properties {
+                deterministic: true,
+                epsilons: false,
+                label_sorted: true,
+                weighted: true,
+                cyclic: true,
+                top_sorted: false,
+                accessible: true,
+                coaccessible: true,
+            },
+            fsa.properties()
+        );
+    }
+
+    #[test]
+    fn determinism() {
+        let fsa = loop_automaton();
+        assert!(fsa.intersect(&fsa).unwrap().is_deterministic());
+
+        let chain = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::one(),
+            },
+        ];
+        assert!(Automaton::from_arcs("q1", vec!["q3"], chain).is_deterministic());
+
+        let branches = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::new(0.1).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2", "q3"], branches);
+        assert!(!fsa.is_deterministic());
+        assert!(fsa.determinize().is_deterministic());
+
+        let epsilons = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: Label::Epsilon,
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs_with_epsilons("q1", vec!["q2"], epsilons);
+        assert!(!fsa.is_deterministic());
+    }
+
+    #[test]
+    fn path_counting() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "b",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        assert_eq!(Some(3), Automaton::from_arcs("q1", vec!["q3"], arcs).count_paths());
+
+        let arcs_ = loop_arcs();
+        assert_eq!(None, Automaton::from_arcs("q1", vec!["q1"], arcs_).count_paths());
+
+        // a chain of n states with two transitions between neighbours has 2^n runs
+        let chain = |n: usize| -> Automaton<&str> {
+            let arcs = (0..n)
+                .flat_map(|state| {
+                    vec!["a", "b"].into_iter().map(move |label| Arc {
+                        from: state,
+                        to: state + 1,
+                        label,
+                        weight: LogDomain::one(),
+                    })
+                })
+                .collect();
+            Automaton::from_arcs(0, vec![n], arcs)
+        };
+        assert_eq!(Some(1 << 63), chain(63).count_paths());
+        assert_eq!(None, chain(64).count_paths());
+    }
+
+    #[test]
+    fn weight_pushing() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::new(0.4).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q3"], arcs);
+        let weight = fsa.weight_of(&["a", "word"]).unwrap();
+
+        for &direction in &[PushDirection::ToInitial, PushDirection::ToFinal] {
+            let pushed = fsa.push_weights(direction);
+            let weight_ = pushed.weight_of(&["a", "word"]).unwrap();
+            assert!((weight.value() - weight_.value()).abs() < 1e-6);
+        }
     }
-}
 
+    #[test]
+    fn shortest_distances() {
+        let arcs = loop_arcs();
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs.clone());
+        let intersection = fsa.intersect(&Automaton::from_arcs("q1", vec!["q1"], arcs))
+            .unwrap();
 
+        // the loop is taken with probability 0.81 arbitrarily often
+        let loops = 1.0 / (1.0 - 0.81);
+        let forward = vec![loops, 0.81 * loops];
+        let backward = vec![loops, loops];
+        for &(reverse, ref distances) in &[(false, forward), (true, backward)] {
+            let distances_ = intersection.shortest_distance(reverse);
+            assert_eq!(distances.len(), distances_.len());
+            for (d, d_) in distances.iter().zip(distances_) {
+                assert!((d - d_.value()).abs() / d < 1e-3);
+            }
+        }
+    }
 
-// tests
+    #[test]
+    fn partition_function() {
+        let arcs = loop_arcs();
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs.clone());
 
-#[cfg(test)]
-mod tests {
-    use fsa::*;
-    use num_traits::One;
+        // geometric series over the words (a word)^n with ratio 0.9
+        let total = fsa.total_weight().unwrap().value();
+        assert!((total - 1.0 / (1.0 - 0.9)).abs() / total < 1e-3);
+
+        let mut arcs = arcs;
+        arcs[0].weight = LogDomain::new(2.0).unwrap();
+        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs.clone());
+        assert_eq!(Err(FsaError::Divergent), fsa.total_weight());
+
+        // the cycle is not on any accepting run
+        let fsa = Automaton::from_arcs("q1", vec!["q3"], arcs);
+        assert_eq!(Ok(LogDomain::zero()), fsa.total_weight());
+    }
 
     #[test]
-    fn simple_fsa() {
+    fn normalization() {
+        let arcs = loop_arcs();
+        let normalized = Automaton::from_arcs("q1", vec!["q1"], arcs.clone())
+            .normalize()
+            .unwrap();
+        assert!((normalized.total_weight().unwrap().value() - 1.0).abs() < 1e-3);
+
+        // the empty word has weight 1/10 of the total weight
+        let (word, weight) = normalized.n_best(1).remove(0);
+        assert_eq!(Vec::<&str>::new(), word);
+        assert!((weight.value() - 0.1).abs() < 1e-3);
+
+        let empty = Automaton::from_arcs("q1", vec!["q3"], arcs);
+        assert_eq!(Err(FsaError::EmptyLanguage), empty.normalize().map(|_| ()));
+    }
+
+    #[test]
+    fn unique_n_best() {
         let arcs = vec![
             Arc {
-                from: "q",
-                to: "q",
-                label: "word",
-                weight: LogDomain::new(0.9).unwrap(),
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.3).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "a",
+                weight: LogDomain::new(0.2).unwrap(),
             },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "b",
+                weight: LogDomain::new(0.1).unwrap(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2", "q3"], arcs);
+
+        assert_eq!(
+            vec![
+                (vec!["a"], LogDomain::new(0.3).unwrap()),
+                (vec!["a"], LogDomain::new(0.2).unwrap()),
+            ],
+            fsa.n_best(2)
+        );
+        assert_eq!(
+            vec![
+                (vec!["a"], LogDomain::new(0.3).unwrap()),
+                (vec!["b"], LogDomain::new(0.1).unwrap()),
+            ],
+            fsa.n_best_unique(2)
+        );
+    }
+
+    #[test]
+    fn best_path() {
+        let arcs = loop_arcs();
+
+        assert_eq!(
+            Some((Vec::new(), LogDomain::one())),
+            Automaton::from_arcs("q1", vec!["q1"], arcs.clone()).best_path()
+        );
+        assert_eq!(
+            Some((vec!["a"], LogDomain::new(0.9).unwrap())),
+            Automaton::from_arcs("q1", vec!["q2"], arcs.clone()).best_path()
+        );
+        assert_eq!(None, Automaton::from_arcs("q1", Vec::new(), arcs).best_path());
+    }
+
+    #[test]
+    fn n_best_words() {
+        let arcs = loop_arcs();
+        let ww = LogDomain::new(0.9).unwrap();
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (Vec::new(), LogDomain::one()),
+            (vec!["a", "word"], ww),
+            (vec!["a", "word", "a", "word"], ww.pow(2.0)),
         ];
+        assert_eq!(words, Automaton::from_arcs("q1", vec!["q1"], arcs).n_best(3));
+
         let arcs_ = vec![
             Arc {
-                from: 0,
-                to: 0,
-                label: "word",
+                from: "q1",
+                to: "q2",
+                label: "a",
                 weight: LogDomain::new(0.9).unwrap(),
             },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "word",
+                weight: LogDomain::new(0.5).unwrap(),
+            },
         ];
-        let fsa = Automaton::from_arcs("q", vec!["q"], arcs);
-
-        assert_eq!((arcs_, 0, vec![0]), fsa.into_arcs());
+        let words_: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (vec!["a"], ww),
+            (vec!["word"], LogDomain::new(0.5).unwrap()),
+        ];
+        assert_eq!(words_, Automaton::from_arcs("q1", vec!["q2"], arcs_).n_best(5));
     }
 
     #[test]
-    fn simple_intersection() {
+    fn tropical_best_paths() {
         let arcs = vec![
             Arc {
                 from: "q1",
                 to: "q2",
                 label: "a",
-                weight: LogDomain::new(0.9).unwrap(),
+                weight: Tropical(2.0),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: Tropical(1.5),
             },
             Arc {
                 from: "q2",
-                to: "q1",
+                to: "q3",
                 label: "word",
-                weight: LogDomain::one(),
+                weight: Tropical(0.5),
+            },
+            Arc {
+                from: "q1",
+                to: "q3",
+                label: "word",
+                weight: Tropical(3.0),
             },
         ];
-        let fsa = Automaton::from_arcs("q1", vec!["q1"], arcs.clone());
-        let fsa_ = Automaton::from_arcs("q1", vec!["q1"], arcs);
+        let fsa = Automaton::from_arcs("q1", vec!["q3"], arcs);
 
-        let arcs_ = vec![
+        assert_eq!(Some((vec!["a", "word"], Tropical(2.0))), fsa.best_path());
+
+        let words = vec![
+            (vec!["a", "word"], Tropical(2.0)),
+            (vec!["a", "word"], Tropical(2.5)),
+            (vec!["word"], Tropical(3.0)),
+        ];
+        assert_eq!(words, fsa.n_best(5));
+    }
+
+    #[test]
+    fn empty_intersection() {
+        let arcs = vec![
             Arc {
-                from: 0,
-                to: 1,
+                from: "q1",
+                to: "q2",
                 label: "a",
-                weight: LogDomain::new(0.9).unwrap().pow(2.0),
+                weight: LogDomain::new(0.9).unwrap(),
             },
+        ];
+        let arcs_ = vec![
             Arc {
-                from: 1,
-                to: 0,
+                from: "q1",
+                to: "q2",
                 label: "word",
                 weight: LogDomain::one(),
             },
         ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs);
+        let fsa_ = fsa.from_arcs_with_same_labels("q1", vec!["q2"], arcs_);
 
         let intersection = fsa.intersect(&fsa_);
-
-        assert_eq!((arcs_, 0, vec![0]), intersection.into_arcs());
+        assert!(intersection.is_ok());
+        assert!(intersection.unwrap().is_empty());
     }
 
     #[test]
@@ -458,6 +4608,338 @@ mod tests {
         assert_eq!(words, language);
     }
 
+    #[test]
+    fn word_iterator() {
+        let arcs = loop_arcs();
+        let language: Vec<(Vec<&str>, LogDomain<f32>)> =
+            Automaton::from_arcs("q1", vec!["q1"], arcs).words().take(4).collect();
+        let ww = LogDomain::new(0.9).unwrap();
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (Vec::new(), LogDomain::one()),
+            (vec!["a", "word"], ww),
+            (vec!["a", "word", "a", "word"], ww.pow(2.0)),
+            (vec!["a", "word", "a", "word", "a", "word"], ww.pow(3.0)),
+        ];
+
+        assert_eq!(words, language);
+    }
+
+    #[test]
+    fn bounded_word_iterator() {
+        let fsa = loop_automaton();
+
+        let words: Vec<(Vec<&str>, LogDomain<f32>)> = vec![
+            (Vec::new(), LogDomain::one()),
+            (vec!["a", "word"], LogDomain::new(0.9).unwrap()),
+        ];
+        assert_eq!(words, fsa.clone().words_up_to(2).unwrap().collect::<Vec<_>>());
+        assert_eq!(words, fsa.clone().words_up_to(3).unwrap().collect::<Vec<_>>());
+        assert_eq!(
+            vec![(Vec::new(), LogDomain::one())],
+            fsa.words_up_to(0).unwrap().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn fallible_arc_listing() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: Tropical(f32::NAN),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q2"], arcs);
+        assert_eq!(Err(FsaError::InvalidWeight(0, 1)), fsa.clone().try_into_arcs());
+
+        // NaN costs are not valid probabilities either
+        let mut binary: Vec<u8> = Vec::new();
+        fsa.write_binary(&mut binary).unwrap();
+        let fsa: Automaton<&str> =
+            Automaton::read_binary(sync::Arc::clone(&fsa.labels), binary.as_slice()).unwrap();
+        assert_eq!(Err(FsaError::InvalidWeight(0, 1)), fsa.try_into_arcs());
+
+        let epsilons = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: Label::Epsilon,
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa: Automaton<&str> = Automaton::from_arcs_with_epsilons("q1", vec!["q2"], epsilons);
+        assert_eq!(Err(FsaError::UnknownLabel(0)), fsa.try_into_arcs());
+    }
+
+    #[test]
+    fn id_bounds() {
+        assert_eq!(Ok(0), checked_id(0));
+        assert_eq!(Ok(c_int::MAX), checked_id(c_int::MAX as usize));
+        assert_eq!(
+            Err(FsaError::IdOverflow(c_int::MAX as usize + 1)),
+            checked_id(c_int::MAX as usize + 1)
+        );
+
+        let fsa: Automaton<&str> = Automaton::from_word(&["a"]);
+        assert_eq!(
+            Some(FsaError::IdOverflow(usize::MAX)),
+            fsa.clone().words_up_to(usize::MAX).err()
+        );
+        assert_eq!(vec![(vec!["a"], LogDomain::one())], fsa.n_best(usize::MAX));
+    }
+
+    #[test]
+    fn validated_construction() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: "word",
+                weight: LogDomain::zero(),
+            },
+        ];
+        assert_eq!(
+            FsaError::InvalidArc(1),
+            Automaton::try_from_arcs("q1", vec!["q1"], arcs.clone()).unwrap_err()
+        );
+
+        let mut arcs = arcs;
+        arcs[1].weight = LogDomain::one();
+        assert_eq!(
+            Automaton::from_arcs("q1", vec!["q1"], arcs.clone()).into_arcs(),
+            Automaton::try_from_arcs("q1", vec!["q1"], arcs).unwrap().into_arcs()
+        );
+    }
+
+    #[test]
+    fn binary_io_from_bytes() {
+        let arcs: Vec<Arc<usize, usize>> = (0..10000)
+            .map(|i| Arc {
+                from: i % 1000,
+                to: (i + 1) % 1000,
+                label: i % 100,
+                weight: LogDomain::new(0.5).unwrap(),
+            })
+            .collect();
+        let fsa = Automaton::from_arcs(0, vec![999], arcs);
+
+        let mut binary: Vec<u8> = Vec::new();
+        fsa.write_binary(&mut binary).unwrap();
+        let from_reader =
+            Automaton::read_binary(sync::Arc::clone(&fsa.labels), binary.as_slice()).unwrap();
+        let from_bytes = Automaton::from_bytes(sync::Arc::clone(&fsa.labels), binary).unwrap();
+
+        let arcs = fsa.into_arcs();
+        assert_eq!(arcs, from_reader.into_arcs());
+        assert_eq!(arcs, from_bytes.into_arcs());
+
+        let labels = sync::Arc::new(HashIntegeriser::new());
+        let error = Automaton::<usize>::from_bytes(labels, vec![0; 64]);
+        assert_eq!(io::ErrorKind::InvalidData, error.err().unwrap().kind());
+    }
+
+    #[test]
+    fn byte_round_trip() {
+        let fsa = loop_automaton();
+
+        let mut binary: Vec<u8> = Vec::new();
+        fsa.write_binary(&mut binary).unwrap();
+        assert_eq!(binary, fsa.to_bytes());
+
+        let fsa_ = Automaton::from_bytes(sync::Arc::clone(&fsa.labels), fsa.to_bytes()).unwrap();
+        assert_eq!(fsa.into_arcs(), fsa_.into_arcs());
+    }
+
+    #[test]
+    fn binary_io() {
+        let fsa = loop_automaton();
+
+        let mut binary: Vec<u8> = Vec::new();
+        fsa.write_binary(&mut binary).unwrap();
+        let fsa_ = Automaton::read_binary(sync::Arc::clone(&fsa.labels), binary.as_slice())
+            .unwrap();
+        assert_eq!(fsa.into_arcs(), fsa_.into_arcs());
+
+        let garbage: Vec<u8> = (0..1024).map(|i| (i * 37 % 251) as u8).collect();
+        let labels: HashIntegeriser<&str> = HashIntegeriser::new();
+        let error = Automaton::<&str>::read_binary(sync::Arc::new(labels), garbage.as_slice())
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn gzipped_binary_files() {
+        let fsa = loop_automaton();
+
+        let mut compressed: Vec<u8> = Vec::new();
+        fsa.write_binary_gz(&mut compressed).unwrap();
+        assert_ne!(fsa.to_bytes(), compressed);
+        let fsa_ = Automaton::read_binary_gz(sync::Arc::clone(&fsa.labels), compressed.as_slice())
+            .unwrap();
+        assert_eq!(fsa.clone().into_arcs(), fsa_.into_arcs());
+
+        let uncompressed = fsa.to_bytes();
+        assert!(
+            Automaton::<&str>::read_binary_gz(sync::Arc::clone(&fsa.labels), uncompressed.as_slice())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn concurrent_access() {
+        let fsa = loop_automaton();
+        let arcs_ = fsa.clone().into_arcs();
+
+        let handles: Vec<thread::JoinHandle<_>> = (0..4)
+            .map(|_| {
+                let fsa_ = fsa.clone();
+                thread::spawn(move || fsa_.into_arcs())
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(arcs_, handle.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn att_reader() {
+        let fsa = loop_automaton();
+
+        let att = format!("3\t5\t1\t{}\n5\t3\t2\n3\n", -LogDomain::new(0.9).unwrap().ln());
+        let fsa_ = Automaton::read_att(sync::Arc::clone(&fsa.labels), att.as_bytes()).unwrap();
+        assert_eq!(fsa.clone().into_arcs(), fsa_.into_arcs());
+
+        let unknown_label = "0\t1\t3\n1\n";
+        let malformed = "0\t1\ta\n1\n";
+        for att_ in &[unknown_label, malformed] {
+            let error = Automaton::<&str>::read_att(sync::Arc::clone(&fsa.labels), att_.as_bytes())
+                .unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        }
+    }
+
+    #[test]
+    fn att_writer() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: "a",
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q3",
+                label: "word",
+                weight: LogDomain::one(),
+            },
+        ];
+        let fsa = Automaton::from_arcs("q1", vec!["q3"], arcs);
+
+        let mut att: Vec<u8> = Vec::new();
+        fsa.write_att(&mut att).unwrap();
+        let expected = format!(
+            "0\t1\t1\t{}\n1\t2\t2\t0\n2\t0\n",
+            -LogDomain::new(0.9).unwrap().ln()
+        );
+        assert_eq!(expected, String::from_utf8(att.clone()).unwrap());
+
+        let fsa_ = Automaton::read_att(sync::Arc::clone(&fsa.labels), att.as_slice()).unwrap();
+        assert_eq!(fsa.into_arcs(), fsa_.into_arcs());
+    }
+
+    #[test]
+    fn dot_export() {
+        let fsa = loop_automaton();
+
+        let mut dot: Vec<u8> = Vec::new();
+        fsa.to_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+
+        assert!(dot.contains("initial -> 0;"));
+        assert!(dot.contains("0 [shape=doublecircle];"));
+        for arc in fsa.into_arcs().0 {
+            let edge = format!(
+                "{} -> {} [label=\"{} / {}\"];",
+                arc.from,
+                arc.to,
+                arc.label,
+                arc.weight
+            );
+            assert!(dot.contains(&edge));
+        }
+    }
+
+    #[test]
+    fn symbols_io() {
+        let fsa = loop_automaton();
+
+        let mut tsv: Vec<u8> = Vec::new();
+        fsa.write_symbols(&mut tsv).unwrap();
+        let symbols = read_symbols(tsv.as_slice()).unwrap();
+
+        assert_eq!(fsa.labels.size(), symbols.size());
+        for label_id in 0..symbols.size() {
+            let label = fsa.labels.find_value(label_id).unwrap().to_string();
+            assert_eq!(Some(&label), symbols.find_value(label_id));
+            assert_eq!(Some(label_id), symbols.find_key(&label));
+        }
+
+        assert!(read_symbols("a\t1\nword\t3\n".as_bytes()).is_err());
+        assert!(read_symbols("a\t0\n".as_bytes()).is_err());
+    }
+
+    // an integerizer whose size exceeds its number of labels
+    struct TruncatedIntegeriser(Vec<String>);
+
+    impl Integeriser for TruncatedIntegeriser {
+        type Item = String;
+
+        fn integerise(&mut self, label: String) -> usize {
+            self.0.push(label);
+            self.0.len() - 1
+        }
+
+        fn find_key(&self, label: &String) -> Option<usize> {
+            self.0.iter().position(|l| l == label)
+        }
+
+        fn find_value(&self, label_id: usize) -> Option<&String> {
+            self.0.get(label_id)
+        }
+
+        fn size(&self) -> usize {
+            self.0.len() + 1
+        }
+    }
+
+    #[test]
+    fn incomplete_symbols() {
+        let labels = TruncatedIntegeriser(vec!["a".to_string()]);
+
+        let mut tsv: Vec<u8> = Vec::new();
+        let error = write_symbol_table(&labels, &mut tsv).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        assert_eq!("a\t1\n".as_bytes(), tsv.as_slice());
+    }
+
+    #[test]
+    fn arc_iterator() {
+        let fsa = loop_automaton();
+        let arcs_: Vec<Arc<usize, Label<&str>>> = fsa.arcs().collect();
+
+        assert_eq!(fsa.into_arcs().0, arcs_);
+    }
+
     #[test]
     fn io() {
         let arcs = vec![
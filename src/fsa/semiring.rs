@@ -0,0 +1,138 @@
+use std::f32;
+use std::fmt::{Display, Error, Formatter};
+use std::ops::{Add, Mul};
+use libc::c_float;
+use log_domain::LogDomain;
+use num_traits::{Float, One, Zero};
+
+/// Weights of the transitions of an `Automaton`.
+/// OpenFst stores each weight as a single float, a `Semiring` determines how
+/// its values are mapped to and from that representation.
+pub trait Semiring: Copy + One {
+    /// Representation of a weight in OpenFst.
+    fn to_openfst(&self) -> c_float;
+    /// Recovers a weight from its representation in OpenFst.
+    fn from_openfst(f: c_float) -> Self;
+
+    /// Recovers a weight from its representation in OpenFst,
+    /// or `None` if it does not represent a weight, e.g. NaN.
+    fn try_from_openfst(f: c_float) -> Option<Self> {
+        if f.is_nan() {
+            None
+        } else {
+            Some(Self::from_openfst(f))
+        }
+    }
+}
+
+/// Probabilities are stored as their negative logarithms.
+/// Costs whose probabilities are out of the range of `f32`, e.g. after pruning,
+/// are recovered without leaving the log domain, so they are neither rounded to
+/// zero nor do they overflow.
+impl Semiring for LogDomain<f32> {
+    fn to_openfst(&self) -> c_float {
+        -self.ln() as c_float
+    }
+
+    fn from_openfst(f: c_float) -> Self {
+        Self::try_from_openfst(f).unwrap()
+    }
+
+    fn try_from_openfst(f: c_float) -> Option<Self> {
+        let value = (-f).exp();
+        if f.is_finite() && (value == 0.0 || value.is_infinite()) {
+            // e^-f computed in the log domain
+            Some(LogDomain::new(f32::consts::E).unwrap().pow(-f))
+        } else {
+            LogDomain::new(value).ok()
+        }
+    }
+}
+
+/// Weights of the tropical semiring, i.e. costs that are added along a run;
+/// among alternative runs, the lowest cost is chosen.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Tropical<F>(pub F);
+
+impl<F: Float> Add for Tropical<F> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Tropical(self.0.min(other.0))
+    }
+}
+
+impl<F: Float> Mul for Tropical<F> {
+    type Output = Self;
+
+    // the product of costs is their sum
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, other: Self) -> Self {
+        Tropical(self.0 + other.0)
+    }
+}
+
+impl<F: Float> Zero for Tropical<F> {
+    fn zero() -> Self {
+        Tropical(F::infinity())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == F::infinity()
+    }
+}
+
+impl<F: Float> One for Tropical<F> {
+    fn one() -> Self {
+        Tropical(F::zero())
+    }
+}
+
+impl<F: Display> Display for Tropical<F> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Costs are stored as they are.
+impl Semiring for Tropical<f32> {
+    fn to_openfst(&self) -> c_float {
+        self.0
+    }
+
+    fn from_openfst(f: c_float) -> Self {
+        Tropical(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tropical_operations() {
+        let (w1, w2) = (Tropical(1.5f32), Tropical(0.5f32));
+
+        assert_eq!(Tropical(0.5), w1 + w2);
+        assert_eq!(Tropical(2.0), w1 * w2);
+        assert_eq!(w1, w1 + Tropical::zero());
+        assert_eq!(w1, w1 * Tropical::one());
+        assert_eq!(w2, Tropical::from_openfst(w2.to_openfst()));
+    }
+
+    #[test]
+    fn extreme_costs() {
+        let w = LogDomain::<f32>::from_openfst(1e6);
+        assert!((w.ln() + 1e6).abs() < 1.0);
+        assert!(w > LogDomain::zero());
+
+        let w = LogDomain::<f32>::from_openfst(-1e6);
+        assert!((w.ln() - 1e6).abs() < 1.0);
+        assert_eq!(LogDomain::zero(), LogDomain::<f32>::from_openfst(c_float::INFINITY));
+        assert_eq!(None, LogDomain::<f32>::try_from_openfst(c_float::NAN));
+
+        // costs within the range of f32 are recovered as before
+        let w = LogDomain::<f32>::from_openfst(-(0.125f32.ln()));
+        assert_eq!(LogDomain::new(0.125).unwrap(), w);
+    }
+}
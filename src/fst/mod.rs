@@ -0,0 +1,395 @@
+use std::hash::Hash;
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::sync;
+use openfsa_sys::*;
+use integeriser::{HashIntegeriser, Integeriser};
+use libc::c_int;
+use log_domain::LogDomain;
+
+use fsa::{checked_id, Arc, Automaton, Semiring};
+
+
+/// Data type for weighted finite state transducers with input labels of type `A`
+/// and output labels of type `B`.
+/// The transitions are `Arc`s whose labels are pairs of an input and an output label;
+/// both tapes are integerized separately.
+/// Like an `Automaton`, a `Transducer` is immutable and its clones share the same
+/// transducer and labels.
+#[derive(Clone)]
+pub struct Transducer<A: Hash + Eq, B: Hash + Eq, W = LogDomain<f32>> {
+    fst: sync::Arc<fsa_t>,
+    input_labels: sync::Arc<HashIntegeriser<A>>,
+    output_labels: sync::Arc<HashIntegeriser<B>>,
+    weights: PhantomData<W>,
+}
+
+impl<A, B, W> Transducer<A, B, W>
+where
+    A: Hash + Eq + Clone,
+    B: Hash + Eq + Clone,
+    W: Semiring,
+{
+    /// Default constructor for a `Transducer`.
+    /// Consumes a list of `Arc` transitions labeled with pairs of an input and an
+    /// output label and stores the labels of each tape in an `Integerizer`.
+    /// The original states of type `Q` are lost after integerization.
+    /// Panics if there are more states or labels than integers in OpenFst.
+    pub fn from_arcs<Q>(
+        initial_state: Q,
+        final_states: Vec<Q>,
+        arcs: Vec<Arc<Q, (A, B), W>>,
+    ) -> Transducer<A, B, W>
+    where
+        Q: Hash + Eq + Clone,
+    {
+        let mut i_states = HashIntegeriser::new();
+        let mut input_labels = HashIntegeriser::new();
+        let mut output_labels = HashIntegeriser::new();
+
+        // ensure initial state = 0, final state in i_states
+        i_states.integerise(initial_state);
+        let mut qfs = Vec::new();
+        for final_state in final_states {
+            qfs.push(checked_id(i_states.integerise(final_state)).unwrap());
+        }
+
+        // zero is reserved for epsilon on both tapes
+        let mut carcs: Vec<fst_arc> = Vec::new();
+        for arc in arcs {
+            let Arc {
+                from,
+                to,
+                label: (input, output),
+                weight,
+            } = arc;
+            carcs.push(fst_arc {
+                from_state: checked_id(i_states.integerise(from)).unwrap(),
+                to_state: checked_id(i_states.integerise(to)).unwrap(),
+                ilabel: checked_id(input_labels.integerise(input) + 1).unwrap(),
+                olabel: checked_id(output_labels.integerise(output) + 1).unwrap(),
+                weight: weight.to_openfst(),
+            });
+        }
+
+        let fst = unsafe {
+            fst_from_arc_list(
+                checked_id(i_states.size()).unwrap(),
+                &*vec_t::new(&mut qfs),
+                &*vec_t::new(&mut carcs),
+            )
+        };
+
+        Transducer {
+            fst: sync::Arc::new(fst),
+            input_labels: sync::Arc::new(input_labels),
+            output_labels: sync::Arc::new(output_labels),
+            weights: PhantomData,
+        }
+    }
+
+    // computes a transducer whose input labels are consistent with `i_labels`
+    // input labels that are missing in `i_labels` are added
+    // panics if there are more labels than integers in OpenFst
+    fn fst_with_input_labels(&self, i_labels: &mut HashIntegeriser<A>) -> sync::Arc<fsa_t> {
+        // zero is reserved for epsilon
+        let mut label_map: Vec<c_int> = vec![0];
+        for label_id in 0..(self.input_labels.size()) {
+            let label = self.input_labels.find_value(label_id).unwrap().clone();
+            label_map.push(checked_id(i_labels.integerise(label) + 1).unwrap());
+        }
+
+        if label_map.iter().enumerate().all(|(i, &l)| i == l as usize) {
+            sync::Arc::clone(&self.fst)
+        } else {
+            let label_map = vec_t::new(&mut label_map);
+            sync::Arc::new(unsafe { fst_relabel_input(self.fst.borrow(), &*label_map) })
+        }
+    }
+
+    /// Composition of two Transducers.
+    /// Returns a `Transducer` that maps each input word of this `Transducer` to the
+    /// output words of `other` for each word that is written by this `Transducer`
+    /// and read by `other`; the weights of both runs are multiplied.
+    /// The output labels of this `Transducer` are matched with the input labels of
+    /// `other` by equality.
+    /// Panics if there are more labels than integers in OpenFst.
+    pub fn compose<C>(&self, other: &Transducer<B, C, W>) -> Transducer<A, C, W>
+    where
+        C: Hash + Eq + Clone,
+    {
+        let other_fst = if sync::Arc::ptr_eq(&self.output_labels, &other.input_labels) {
+            sync::Arc::clone(&other.fst)
+        } else {
+            // labels that are only read by `other` are added, but never matched
+            let mut middle_labels = (*self.output_labels).clone();
+            other.fst_with_input_labels(&mut middle_labels)
+        };
+
+        Transducer {
+            fst: sync::Arc::new(unsafe { fst_compose(self.fst.borrow(), other_fst.borrow()) }),
+            input_labels: sync::Arc::clone(&self.input_labels),
+            output_labels: sync::Arc::clone(&other.output_labels),
+            weights: PhantomData,
+        }
+    }
+
+    /// Projection of a `Transducer` onto its input tape.
+    /// Returns an `Automaton` that recognizes the input words of this `Transducer`
+    /// with the weights of their runs; the output labels are dropped.
+    pub fn project_input(&self) -> Automaton<A, W> {
+        let fsa = unsafe { fst_project(self.fst.borrow(), 0) };
+        Automaton::from_fsa(fsa, sync::Arc::clone(&self.input_labels))
+    }
+
+    /// Projection of a `Transducer` onto its output tape.
+    /// Returns an `Automaton` that recognizes the output words of this `Transducer`
+    /// with the weights of their runs; the input labels are dropped.
+    pub fn project_output(&self) -> Automaton<B, W> {
+        let fsa = unsafe { fst_project(self.fst.borrow(), 1) };
+        Automaton::from_fsa(fsa, sync::Arc::clone(&self.output_labels))
+    }
+
+    /// Applies a `Transducer` to an input word.
+    /// Returns an `Automaton` that recognizes the output words of all runs
+    /// on `input` with their weights.
+    /// If `input` contains an unknown label, the language is empty.
+    /// Panics if `input` is longer than the integers in OpenFst.
+    pub fn apply(&self, input: &[A]) -> Automaton<B, W> {
+        // each state is at most the number of states, so they can be cast below
+        let states = checked_id(input.len() + 1).unwrap();
+
+        // linear transducer that reads and writes `input`
+        let mut carcs: Vec<fst_arc> = Vec::new();
+        let mut qfs: Vec<c_int> = vec![states - 1];
+        for (state, label) in input.iter().enumerate() {
+            if let Some(label_id) = self.input_labels.find_key(label) {
+                let label_id = checked_id(label_id + 1).unwrap();
+                carcs.push(fst_arc {
+                    from_state: state as c_int,
+                    to_state: (state + 1) as c_int,
+                    ilabel: label_id,
+                    olabel: label_id,
+                    weight: W::one().to_openfst(),
+                });
+            } else {
+                // no run reads an unknown label
+                qfs.clear();
+                break;
+            }
+        }
+
+        let fsa = unsafe {
+            let word = fst_from_arc_list(
+                states,
+                &*vec_t::new(&mut qfs),
+                &*vec_t::new(&mut carcs),
+            );
+            let composition = fst_compose(&word, self.fst.borrow());
+            fst_project(&composition, 1)
+        };
+        Automaton::from_fsa(fsa, sync::Arc::clone(&self.output_labels))
+    }
+
+    // decodes an integerized transition
+    fn decode_arc(&self, carc: &fst_arc) -> Arc<usize, (A, B), W> {
+        let &fst_arc {
+            from_state,
+            to_state,
+            ilabel,
+            olabel,
+            weight,
+        } = carc;
+
+        Arc {
+            from: from_state as usize,
+            to: to_state as usize,
+            label: (
+                self.input_labels
+                    .find_value((ilabel - 1) as usize)
+                    .unwrap()
+                    .clone(),
+                self.output_labels
+                    .find_value((olabel - 1) as usize)
+                    .unwrap()
+                    .clone(),
+            ),
+            weight: W::from_openfst(weight),
+        }
+    }
+
+    /// Lists the `Arc`s of a `Transducer` with their pairs of input and output labels.
+    /// Since the original type of states cannot be recovered, we use `usize`.
+    #[allow(clippy::type_complexity)]
+    pub fn into_arcs(self) -> (Vec<Arc<usize, (A, B), W>>, usize, Vec<usize>) {
+        let carcs = unsafe { fst_to_arc_list(self.fst.borrow()) };
+        let (q0, qfs): (c_int, Vec<c_int>) = unsafe {
+            let qi = fsa_initial_state(self.fst.borrow());
+            let qfs = fsa_final_states(self.fst.borrow());
+
+            (qi, qfs.to_vec())
+        };
+
+        (
+            carcs
+                .as_slice::<fst_arc>()
+                .iter()
+                .map(|carc| self.decode_arc(carc))
+                .collect(),
+            q0 as usize,
+            qfs.into_iter().map(|x| x as usize).collect(),
+        )
+    }
+}
+
+
+
+// tests
+
+#[cfg(test)]
+mod tests {
+    use fst::*;
+    use fsa::Label;
+    use num_traits::One;
+
+    // a loop that translates "a" to 'a' with weight 0.9 from q1 to q2 and "word" to 'w'
+    // back to q1
+    fn loop_arcs() -> Vec<Arc<&'static str, (&'static str, char)>> {
+        vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: ("a", 'a'),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: "q2",
+                to: "q1",
+                label: ("word", 'w'),
+                weight: LogDomain::one(),
+            },
+        ]
+    }
+
+    #[test]
+    fn simple_transducer() {
+        let arcs_ = vec![
+            Arc {
+                from: 0,
+                to: 1,
+                label: ("a", 'a'),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: 1,
+                to: 0,
+                label: ("word", 'w'),
+                weight: LogDomain::one(),
+            },
+        ];
+        let fst = Transducer::from_arcs("q1", vec!["q1"], loop_arcs());
+
+        assert_eq!((arcs_, 0, vec![0]), fst.into_arcs());
+    }
+
+    #[test]
+    fn composition() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: ("a", "b"),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        let arcs_ = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: ("d", "e"),
+                weight: LogDomain::new(0.5).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: ("b", "c"),
+                weight: LogDomain::one(),
+            },
+        ];
+        let fst = Transducer::from_arcs("q1", vec!["q2"], arcs);
+        let fst_ = Transducer::from_arcs("q1", vec!["q2"], arcs_);
+
+        let composition = vec![
+            Arc {
+                from: 0,
+                to: 1,
+                label: ("a", "c"),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+        ];
+        assert_eq!((composition, 0, vec![1]), fst.compose(&fst_).into_arcs());
+    }
+
+    #[test]
+    fn projection() {
+        let fst = Transducer::from_arcs("q1", vec!["q1"], loop_arcs());
+
+        let input = vec![
+            Arc {
+                from: 0,
+                to: 1,
+                label: Label::Symbol("a"),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: 1,
+                to: 0,
+                label: Label::Symbol("word"),
+                weight: LogDomain::one(),
+            },
+        ];
+        let output = vec![
+            Arc {
+                from: 0,
+                to: 1,
+                label: Label::Symbol('a'),
+                weight: LogDomain::new(0.9).unwrap(),
+            },
+            Arc {
+                from: 1,
+                to: 0,
+                label: Label::Symbol('w'),
+                weight: LogDomain::one(),
+            },
+        ];
+        assert_eq!((input, 0, vec![0]), fst.project_input().into_arcs());
+        assert_eq!((output, 0, vec![0]), fst.project_output().into_arcs());
+    }
+
+    #[test]
+    fn application() {
+        let arcs = vec![
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: ("a", "b"),
+                weight: LogDomain::new(0.6).unwrap(),
+            },
+            Arc {
+                from: "q1",
+                to: "q2",
+                label: ("a", "c"),
+                weight: LogDomain::new(0.4).unwrap(),
+            },
+        ];
+        let fst = Transducer::from_arcs("q1", vec!["q2"], arcs);
+
+        let outputs = vec![
+            (vec!["b"], LogDomain::new(0.6).unwrap()),
+            (vec!["c"], LogDomain::new(0.4).unwrap()),
+        ];
+        assert_eq!(outputs, fst.apply(&["a"]).n_best(5));
+        assert!(fst.apply(&["a", "a"]).is_empty());
+        assert!(fst.apply(&["d"]).is_empty());
+    }
+}
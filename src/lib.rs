@@ -3,6 +3,15 @@ extern crate libc;
 extern crate log_domain;
 extern crate num_traits;
 extern crate openfsa_sys;
+extern crate rand;
 extern crate serde;
+#[cfg(feature = "flate2")]
+extern crate flate2;
+
+#[cfg(test)]
+extern crate bincode;
+#[cfg(test)]
+extern crate serde_json;
 
 pub mod fsa;
+pub mod fst;